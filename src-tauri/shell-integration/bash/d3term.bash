@@ -0,0 +1,61 @@
+# d3term shell integration for bash, passed as --rcfile.
+# Source the user's startup files first, exactly as bash would without --rcfile.
+if [ -n "$D3TERM_BASH_LOGIN" ]; then
+  unset D3TERM_BASH_LOGIN
+  if [ -r /etc/profile ]; then
+    . /etc/profile
+  fi
+  for __d3term_file in "$HOME/.bash_profile" "$HOME/.bash_login" "$HOME/.profile"; do
+    if [ -r "$__d3term_file" ]; then
+      . "$__d3term_file"
+      break
+    fi
+  done
+  unset __d3term_file
+elif [ -r "$HOME/.bashrc" ]; then
+  . "$HOME/.bashrc"
+fi
+
+if [[ $- == *i* ]] && [ -z "$D3TERM_SHELL_INTEGRATION_LOADED" ]; then
+  D3TERM_SHELL_INTEGRATION_LOADED=1
+  __d3term_command_running=0
+  __d3term_ready=0
+
+  __d3term_status() {
+    local code=$?
+    if [ "$__d3term_command_running" = 1 ]; then
+      printf '\e]133;D;%s\a' "$code"
+      __d3term_command_running=0
+    fi
+    return $code
+  }
+
+  __d3term_prompt() {
+    local code=$?
+    printf '\e]7;file://%s%s\a' "$HOSTNAME" "${PWD//%/%25}"
+    printf '\e]133;A\a'
+    case "$PS1" in
+      *$'\e]133;B\a'*) ;;
+      *) PS1="$PS1"'\[\e]133;B\a\]' ;;
+    esac
+    __d3term_ready=1
+    return $code
+  }
+
+  __d3term_preexec() {
+    case "$BASH_COMMAND" in
+      __d3term_*) return ;;
+    esac
+    if [ "$__d3term_ready" = 1 ] && [ -z "$COMP_LINE" ]; then
+      __d3term_ready=0
+      __d3term_command_running=1
+      printf '\e]133;C\a'
+    fi
+  }
+
+  PROMPT_COMMAND="__d3term_status${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __d3term_prompt"
+  # Leave an existing DEBUG trap alone; only the command-start mark is lost in that case.
+  if [ -z "$(trap -p DEBUG)" ]; then
+    trap '__d3term_preexec' DEBUG
+  fi
+fi
@@ -0,0 +1,34 @@
+# d3term shell integration for fish, loaded from vendor_conf.d via XDG_DATA_DIRS.
+if set -q D3TERM_FISH_XDG_DATA_DIRS
+    if test -n "$D3TERM_FISH_XDG_DATA_DIRS"
+        set -gx XDG_DATA_DIRS $D3TERM_FISH_XDG_DATA_DIRS
+    else
+        set -e XDG_DATA_DIRS
+    end
+    set -e D3TERM_FISH_XDG_DATA_DIRS
+end
+
+if status is-interactive; and not set -q D3TERM_SHELL_INTEGRATION_LOADED
+    set -g D3TERM_SHELL_INTEGRATION_LOADED 1
+
+    function __d3term_prompt --on-event fish_prompt
+        printf '\e]7;file://%s%s\a' $hostname (string replace -a '%' '%25' -- $PWD)
+        printf '\e]133;A\a'
+        # config.fish is read after vendor_conf.d, so wrap the prompt once it is final.
+        if not functions -q __d3term_original_fish_prompt
+            functions -c fish_prompt __d3term_original_fish_prompt
+            function fish_prompt
+                __d3term_original_fish_prompt
+                printf '\e]133;B\a'
+            end
+        end
+    end
+
+    function __d3term_preexec --on-event fish_preexec
+        printf '\e]133;C\a'
+    end
+
+    function __d3term_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+end
@@ -1,30 +1,223 @@
-use tauri::{AppHandle, State};
+use std::path::PathBuf;
 
-use crate::{pty::SessionInfo, state::AppState};
+use tauri::{ipc::Channel, AppHandle, Manager, State, Window};
+
+use crate::{
+    config::ProfileSummary,
+    foreground::ForegroundProcess,
+    history::CommandRecord,
+    pty::{PlaybackInfo, PlaybackOptions, SessionId, SessionInfo, StartOptions},
+    screen::ScreenSnapshot,
+    shutdown::ProcessEnd,
+    state::AppState,
+    workdir::StartCwd,
+};
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn start_session(
     app: AppHandle,
+    window: Window,
     state: State<'_, AppState>,
     cols: u16,
     rows: u16,
+    profile: Option<String>,
+    cwd: Option<StartCwd>,
+    on_data: Channel,
 ) -> Result<SessionInfo, String> {
     let config = state.config.current();
     state.config.emit_current(&app)?;
-    state.session.start(&app, &config.startup, cols, rows)
+    // Replacing a session under the same ID is kept to restarts; a caller-chosen ID could
+    // collide with one the counter hands out later.
+    let options = StartOptions {
+        session_id: None,
+        window: window.label().to_string(),
+        cols,
+        rows,
+        profile,
+        cwd,
+        restarts: 0,
+    };
+    state.session.start(&app, &config, options, on_data)
+}
+
+#[tauri::command]
+pub fn list_profiles(state: State<'_, AppState>) -> Vec<ProfileSummary> {
+    state.config.current().profile_summaries()
+}
+
+#[tauri::command]
+pub fn attach_session(
+    window: Window,
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    cols: u16,
+    rows: u16,
+    on_data: Channel,
+) -> Result<SessionInfo, String> {
+    state
+        .session
+        .attach(session_id, window.label().to_string(), cols, rows, on_data)
+}
+
+#[tauri::command]
+pub fn write_stdin(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    data: String,
+) -> Result<(), String> {
+    state.session.write_stdin(session_id, data.into_bytes())
+}
+
+#[tauri::command]
+pub fn write_stdin_bytes(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    state.session.write_stdin(session_id, data)
+}
+
+#[tauri::command]
+pub fn resize(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    cols: u16,
+    rows: u16,
+) -> Result<(), String> {
+    state.session.resize(session_id, cols, rows)
+}
+
+#[tauri::command]
+pub fn send_signal(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    signal: String,
+) -> Result<u32, String> {
+    state.session.send_signal(session_id, &signal)
+}
+
+// A graceful stop sleeps through its grace periods, so it runs on a blocking thread rather
+// than on the main thread or an async worker.
+#[tauri::command]
+pub async fn stop_session(
+    app: AppHandle,
+    session_id: SessionId,
+    force: Option<bool>,
+) -> Result<Option<ProcessEnd>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        state.session.stop(session_id, force.unwrap_or(false))
+    })
+    .await
+    .map_err(|err| format!("failed to stop session {session_id}: {err}"))?
+}
+
+#[tauri::command]
+pub fn get_foreground_process(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+) -> Result<Option<ForegroundProcess>, String> {
+    state.session.foreground_process(session_id)
+}
+
+#[tauri::command]
+pub fn close_window(window: Window) -> Result<(), String> {
+    window
+        .destroy()
+        .map_err(|err| format!("failed to close window: {err}"))
+}
+
+#[tauri::command]
+pub fn ack_output(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    bytes: usize,
+) -> Result<(), String> {
+    state.session.ack_output(session_id, bytes)
 }
 
 #[tauri::command]
-pub fn write_stdin(state: State<'_, AppState>, data: String) -> Result<(), String> {
-    state.session.write_stdin(data)
+pub fn get_terminal_state(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    include_scrollback: Option<bool>,
+) -> Result<ScreenSnapshot, String> {
+    state
+        .session
+        .terminal_state(session_id, include_scrollback.unwrap_or(false))
 }
 
 #[tauri::command]
-pub fn resize(state: State<'_, AppState>, cols: u16, rows: u16) -> Result<(), String> {
-    state.session.resize(cols, rows)
+pub fn get_session_cwd(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+) -> Result<Option<String>, String> {
+    state.session.session_cwd(session_id)
 }
 
 #[tauri::command]
-pub fn stop_session(state: State<'_, AppState>) -> Result<(), String> {
-    state.session.stop()
+pub fn list_commands(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+) -> Result<Vec<CommandRecord>, String> {
+    state.session.list_commands(session_id)
+}
+
+#[tauri::command]
+pub fn get_command_output(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    index: usize,
+) -> Result<String, String> {
+    state.session.command_output(session_id, index)
+}
+
+#[tauri::command]
+pub fn start_recording(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+) -> Result<String, String> {
+    let config = state.config.current();
+    state.session.start_recording(&config.recording, session_id)
+}
+
+#[tauri::command]
+pub fn stop_recording(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+) -> Result<Option<String>, String> {
+    state.session.stop_recording(session_id)
+}
+
+#[tauri::command]
+pub fn start_playback(
+    app: AppHandle,
+    window: Window,
+    state: State<'_, AppState>,
+    path: String,
+    speed: Option<f64>,
+    idle_time_limit: Option<f64>,
+    on_data: Channel,
+) -> Result<PlaybackInfo, String> {
+    let config = state.config.current();
+    let options = PlaybackOptions {
+        path: PathBuf::from(path),
+        window: window.label().to_string(),
+        speed: speed.unwrap_or(1.0),
+        idle_time_limit,
+    };
+    state
+        .session
+        .start_playback(&app, &config, options, on_data)
+}
+
+#[tauri::command]
+pub fn control_playback(
+    state: State<'_, AppState>,
+    session_id: SessionId,
+    paused: Option<bool>,
+    speed: Option<f64>,
+) -> Result<(), String> {
+    state.session.control_playback(session_id, paused, speed)
 }
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     env, fs,
     path::{Path, PathBuf},
     sync::{mpsc, Arc, Mutex, RwLock},
@@ -26,6 +27,21 @@ impl Default for MultiplexerMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnExit {
+    Hold,
+    Close,
+    Restart,
+    RestartOnFailure,
+}
+
+impl Default for OnExit {
+    fn default() -> Self {
+        Self::Hold
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ThemeMode {
@@ -48,6 +64,11 @@ pub struct StartupConfig {
     pub shell_args: Vec<String>,
     pub zellij_command: String,
     pub tmux_command: String,
+    pub shell_integration: bool,
+    pub on_exit: OnExit,
+    pub command: Option<String>,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
 }
 
 impl Default for StartupConfig {
@@ -58,10 +79,63 @@ impl Default for StartupConfig {
             shell_args: Vec::new(),
             zellij_command: "zellij attach -c d3term".to_string(),
             tmux_command: "tmux new-session -A -s main".to_string(),
+            shell_integration: false,
+            on_exit: OnExit::Hold,
+            command: None,
+            cwd: None,
+            env: BTreeMap::new(),
         }
     }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProfileConfig {
+    pub multiplexer: Option<MultiplexerMode>,
+    pub shell: Option<String>,
+    pub shell_args: Option<Vec<String>>,
+    pub command: Option<String>,
+    pub cwd: Option<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl ProfileConfig {
+    pub fn apply(&self, base: &StartupConfig) -> StartupConfig {
+        let mut startup = base.clone();
+        if let Some(multiplexer) = self.multiplexer.as_ref() {
+            startup.multiplexer = multiplexer.clone();
+        }
+        if let Some(shell) = self.shell.as_ref() {
+            startup.shell = Some(shell.clone());
+        }
+        if let Some(shell_args) = self.shell_args.as_ref() {
+            startup.shell_args = shell_args.clone();
+        }
+        // A profile that picks its own shell or multiplexer should not inherit [startup].command.
+        if self.command.is_some() || self.multiplexer.is_some() || self.shell.is_some() {
+            startup.command = self.command.clone();
+        }
+        if let Some(cwd) = self.cwd.as_ref() {
+            startup.cwd = Some(cwd.clone());
+        }
+        startup.env.extend(self.env.clone());
+        startup
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedProfile {
+    pub name: Option<String>,
+    pub startup: StartupConfig,
+    pub warning: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct TerminalConfig {
@@ -86,20 +160,239 @@ impl Default for TerminalConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub title_template: String,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title_template: "{title} — {command} ({cwd})".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PerformanceConfig {
+    pub output_batch_ms: u64,
+    pub output_batch_bytes: usize,
+    pub output_high_water_mark: usize,
+    pub replay_buffer_bytes: usize,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            output_batch_ms: 8,
+            output_batch_bytes: 128 * 1024,
+            output_high_water_mark: 512 * 1024,
+            replay_buffer_bytes: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    pub hangup_timeout_ms: u64,
+    pub terminate_timeout_ms: u64,
+    pub confirm_close: bool,
+    pub idle_processes: Vec<String>,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            hangup_timeout_ms: 2000,
+            terminate_timeout_ms: 1000,
+            confirm_close: true,
+            idle_processes: [
+                "zsh", "bash", "fish", "sh", "dash", "ksh", "tcsh", "nu", "zellij", "tmux",
+                "screen",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+    pub directory: String,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "recordings".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    Raw,
+    Plain,
+}
+
+impl Default for TranscriptFormat {
+    fn default() -> Self {
+        Self::Plain
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub enabled: bool,
+    pub format: TranscriptFormat,
+    pub directory: String,
+    pub file_name: String,
+    pub max_file_bytes: u64,
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: TranscriptFormat::Plain,
+            directory: "logs".to_string(),
+            file_name: "{date}-session{session_id}-{command}.log".to_string(),
+            max_file_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    pub builtin_rules: bool,
+    pub rules: Vec<String>,
+    pub marker: String,
+    pub suppress_no_echo: bool,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            builtin_rules: true,
+            rules: Vec::new(),
+            marker: "[REDACTED]".to_string(),
+            suppress_no_echo: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub long_command_seconds: u64,
+    pub osc_notifications: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            long_command_seconds: 10,
+            osc_notifications: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct AppConfig {
+    pub default_profile: Option<String>,
     pub startup: StartupConfig,
     pub terminal: TerminalConfig,
+    pub window: WindowConfig,
+    pub performance: PerformanceConfig,
+    pub shutdown: ShutdownConfig,
+    pub recording: RecordingConfig,
+    pub logging: LoggingConfig,
+    pub redaction: RedactionConfig,
+    pub notifications: NotificationConfig,
+    pub profiles: BTreeMap<String, ProfileConfig>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            default_profile: None,
             startup: StartupConfig::default(),
             terminal: TerminalConfig::default(),
+            window: WindowConfig::default(),
+            performance: PerformanceConfig::default(),
+            shutdown: ShutdownConfig::default(),
+            recording: RecordingConfig::default(),
+            logging: LoggingConfig::default(),
+            redaction: RedactionConfig::default(),
+            notifications: NotificationConfig::default(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn select_profile(&self, requested: Option<&str>) -> Result<SelectedProfile, String> {
+        if let Some(name) = requested {
+            let profile = self
+                .profiles
+                .get(name)
+                .ok_or_else(|| format!("unknown profile: {name}"))?;
+            return Ok(SelectedProfile {
+                name: Some(name.to_string()),
+                startup: profile.apply(&self.startup),
+                warning: None,
+            });
+        }
+
+        let Some(name) = self.default_profile.as_deref() else {
+            return Ok(SelectedProfile {
+                name: None,
+                startup: self.startup.clone(),
+                warning: None,
+            });
+        };
+        match self.profiles.get(name) {
+            Some(profile) => Ok(SelectedProfile {
+                name: Some(name.to_string()),
+                startup: profile.apply(&self.startup),
+                warning: None,
+            }),
+            None => Ok(SelectedProfile {
+                name: None,
+                startup: self.startup.clone(),
+                warning: Some(format!(
+                    "default_profile のプロファイル {name} が見つからないため [startup] で起動します"
+                )),
+            }),
         }
     }
+
+    pub fn profile_summaries(&self) -> Vec<ProfileSummary> {
+        self.profiles
+            .keys()
+            .map(|name| ProfileSummary {
+                name: name.clone(),
+                is_default: self.default_profile.as_deref() == Some(name.as_str()),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -248,6 +541,33 @@ pub fn resolve_config_path_with_env(xdg: Option<&str>, home: Option<&str>) -> Pa
     PathBuf::from(".config").join("d3term").join("config.toml")
 }
 
+pub fn resolve_state_dir() -> PathBuf {
+    let xdg = env::var("XDG_STATE_HOME").ok();
+    let home = env::var("HOME").ok();
+    resolve_state_dir_with_env(xdg.as_deref(), home.as_deref())
+}
+
+pub fn resolve_state_dir_with_env(xdg: Option<&str>, home: Option<&str>) -> PathBuf {
+    if let Some(xdg_state) = xdg {
+        let xdg_state = xdg_state.trim();
+        if !xdg_state.is_empty() {
+            return PathBuf::from(xdg_state).join("d3term");
+        }
+    }
+
+    if let Some(home_dir) = home {
+        let home_dir = home_dir.trim();
+        if !home_dir.is_empty() {
+            return PathBuf::from(home_dir)
+                .join(".local")
+                .join("state")
+                .join("d3term");
+        }
+    }
+
+    PathBuf::from(".local").join("state").join("d3term")
+}
+
 fn resolve_watch_root(config_path: &Path) -> PathBuf {
     if let Some(parent) = config_path.parent() {
         if parent.exists() {
@@ -291,6 +611,15 @@ mod tests {
         assert_eq!(path, PathBuf::from("/tmp/home/.config/d3term/config.toml"));
     }
 
+    #[test]
+    fn resolve_state_dir_prefers_xdg_state_home() {
+        let path = resolve_state_dir_with_env(Some("/tmp/state"), Some("/tmp/home"));
+        assert_eq!(path, PathBuf::from("/tmp/state/d3term"));
+
+        let path = resolve_state_dir_with_env(None, Some("/tmp/home"));
+        assert_eq!(path, PathBuf::from("/tmp/home/.local/state/d3term"));
+    }
+
     #[test]
     fn parse_with_partial_fields_uses_defaults() {
         let raw = r#"
@@ -307,6 +636,113 @@ mod tests {
         assert_eq!(parsed.terminal.font_size, 15.0);
         assert_eq!(parsed.terminal.letter_spacing, -1.0);
         assert_eq!(parsed.terminal.line_height, 1.2);
+        assert_eq!(parsed.performance, PerformanceConfig::default());
+    }
+
+    #[test]
+    fn parse_performance_section() {
+        let raw = r#"
+            [performance]
+            output_batch_ms = 16
+            output_high_water_mark = 0
+        "#;
+
+        let parsed = toml::from_str::<AppConfig>(raw).expect("should parse");
+        assert_eq!(parsed.performance.output_batch_ms, 16);
+        assert_eq!(parsed.performance.output_batch_bytes, 128 * 1024);
+        assert_eq!(parsed.performance.output_high_water_mark, 0);
+    }
+
+    #[test]
+    fn parse_logging_section() {
+        let raw = r#"
+            [logging]
+            enabled = true
+            format = "raw"
+            max_files = 0
+        "#;
+
+        let parsed = toml::from_str::<AppConfig>(raw).expect("should parse");
+        assert!(parsed.logging.enabled);
+        assert_eq!(parsed.logging.format, TranscriptFormat::Raw);
+        assert_eq!(parsed.logging.max_files, 0);
+        assert_eq!(parsed.logging.directory, "logs");
+    }
+
+    #[test]
+    fn parse_on_exit_policy() {
+        let raw = r#"
+            [startup]
+            on_exit = "restart-on-failure"
+        "#;
+
+        let parsed = toml::from_str::<AppConfig>(raw).expect("should parse");
+        assert_eq!(parsed.startup.on_exit, OnExit::RestartOnFailure);
+        assert_eq!(AppConfig::default().startup.on_exit, OnExit::Hold);
+    }
+
+    #[test]
+    fn profiles_override_startup() {
+        let raw = r#"
+            default_profile = "work"
+
+            [startup]
+            multiplexer = "zellij"
+            command = "ignored when a profile picks its own shell"
+
+            [startup.env]
+            EDITOR = "vim"
+
+            [profiles.work]
+            multiplexer = "tmux"
+
+            [profiles.prod]
+            command = "docker exec -it prod bash"
+            cwd = "/srv"
+            env = { EDITOR = "nano", STAGE = "prod" }
+        "#;
+
+        let parsed = toml::from_str::<AppConfig>(raw).expect("should parse");
+        let work = parsed.select_profile(None).expect("default profile");
+        assert_eq!(work.name.as_deref(), Some("work"));
+        assert_eq!(work.startup.multiplexer, MultiplexerMode::Tmux);
+        assert_eq!(work.startup.command, None);
+
+        let prod = parsed.select_profile(Some("prod")).expect("prod profile");
+        assert_eq!(
+            prod.startup.command.as_deref(),
+            Some("docker exec -it prod bash")
+        );
+        assert_eq!(prod.startup.cwd.as_deref(), Some("/srv"));
+        assert_eq!(prod.startup.env["EDITOR"], "nano");
+        assert_eq!(prod.startup.env["STAGE"], "prod");
+
+        assert!(parsed.select_profile(Some("missing")).is_err());
+        assert_eq!(
+            parsed.profile_summaries(),
+            vec![
+                ProfileSummary {
+                    name: "prod".to_string(),
+                    is_default: false,
+                },
+                ProfileSummary {
+                    name: "work".to_string(),
+                    is_default: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_default_profile_falls_back_to_startup() {
+        let config = AppConfig {
+            default_profile: Some("gone".to_string()),
+            ..AppConfig::default()
+        };
+        let selected = config.select_profile(None).expect("fallback");
+        assert_eq!(selected.name, None);
+        assert_eq!(selected.startup, config.startup);
+        assert!(selected.warning.is_some());
     }
 
     #[test]
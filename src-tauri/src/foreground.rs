@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::pty::SessionId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ForegroundProcess {
+    pub session_id: SessionId,
+    pub pgid: u32,
+    pub name: Option<String>,
+    pub idle: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CloseRequestedPayload {
+    pub processes: Vec<ForegroundProcess>,
+}
+
+impl ForegroundProcess {
+    pub fn new(
+        session_id: SessionId,
+        pgid: u32,
+        shell_pid: Option<u32>,
+        idle_processes: &[String],
+    ) -> Self {
+        let name = process_name(pgid);
+        // The direct child in the foreground means the shell (or multiplexer) is at its prompt.
+        let idle = shell_pid == Some(pgid)
+            || name
+                .as_deref()
+                .is_some_and(|name| is_idle_process(name, idle_processes));
+        Self {
+            session_id,
+            pgid,
+            name,
+            idle,
+        }
+    }
+}
+
+pub fn is_idle_process(name: &str, idle_processes: &[String]) -> bool {
+    let name = name.trim().trim_start_matches('-');
+    let name = Path::new(name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(name);
+    idle_processes.iter().any(|idle| idle == name)
+}
+
+#[cfg(target_os = "linux")]
+fn process_name(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{pid}/comm"))
+        .ok()
+        .map(|name| name.trim_end().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_name(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "comm=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !name.is_empty()).then_some(name)
+}
+
+#[cfg(not(unix))]
+fn process_name(_pid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle_list() -> Vec<String> {
+        ["zsh", "zellij"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn idle_processes_match_by_base_name() {
+        assert!(is_idle_process("zsh", &idle_list()));
+        assert!(is_idle_process("-zsh", &idle_list()));
+        assert!(is_idle_process("/opt/homebrew/bin/zellij", &idle_list()));
+        assert!(!is_idle_process("git", &idle_list()));
+        assert!(!is_idle_process("zsh-helper", &idle_list()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn current_process_is_named_from_proc() {
+        let pid = std::process::id();
+        let process = ForegroundProcess::new(1, pid, None, &[]);
+        assert!(process.name.is_some());
+        assert!(!process.idle);
+        assert!(ForegroundProcess::new(1, pid, Some(pid), &[]).idle);
+    }
+}
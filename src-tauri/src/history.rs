@@ -0,0 +1,200 @@
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandRecord {
+    pub index: usize,
+    pub prompt_line: usize,
+    pub command: String,
+    pub cwd: Option<String>,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub exit_code: Option<i32>,
+    #[serde(skip)]
+    command_start: Option<Position>,
+    #[serde(skip)]
+    output_start: Option<Position>,
+    #[serde(skip)]
+    output: Option<String>,
+}
+
+impl CommandRecord {
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+}
+
+pub struct CommandHistory {
+    records: VecDeque<CommandRecord>,
+    next_index: usize,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+            next_index: 0,
+        }
+    }
+
+    pub fn prompt_start(&mut self, line: usize, unfinished_output: Option<String>) {
+        // A command that never reported its end (interrupted, or the shell dropped its hooks)
+        // is closed out here, so its output is not read from whatever runs after it.
+        if let Some(record) = self.running_mut() {
+            record.output = Some(unfinished_output.unwrap_or_default());
+            record.finished_at = Some(now_millis());
+        }
+        // A prompt that never ran a command (empty Enter, Ctrl-C) is replaced, not kept.
+        if self
+            .records
+            .back()
+            .is_some_and(|record| record.output_start.is_none())
+        {
+            self.records.pop_back();
+        }
+        if self.records.len() == HISTORY_LIMIT {
+            self.records.pop_front();
+        }
+        self.records.push_back(CommandRecord {
+            index: self.next_index,
+            prompt_line: line,
+            command: String::new(),
+            cwd: None,
+            started_at: None,
+            finished_at: None,
+            exit_code: None,
+            command_start: None,
+            output_start: None,
+            output: None,
+        });
+        self.next_index += 1;
+    }
+
+    pub fn command_start(&mut self, position: Position) {
+        if let Some(record) = self.awaiting_command() {
+            record.command_start = Some(position);
+        }
+    }
+
+    pub fn command_start_position(&self) -> Option<Position> {
+        self.records
+            .back()
+            .filter(|record| record.output_start.is_none())
+            .and_then(|record| record.command_start)
+    }
+
+    pub fn output_start(&mut self, position: Position, command: String, cwd: Option<String>) {
+        if let Some(record) = self.awaiting_command() {
+            record.command = command;
+            record.cwd = cwd;
+            record.output_start = Some(position);
+            record.started_at = Some(now_millis());
+        }
+    }
+
+    pub fn running_output_start(&self) -> Option<Position> {
+        self.running().and_then(|record| record.output_start)
+    }
+
+    pub fn finish(&mut self, output: String, exit_code: Option<i32>) -> Option<&CommandRecord> {
+        let record = self.running_mut()?;
+        record.output = Some(output);
+        record.exit_code = exit_code;
+        record.finished_at = Some(now_millis());
+        Some(record)
+    }
+
+    pub fn records(&self) -> Vec<CommandRecord> {
+        self.records
+            .iter()
+            .filter(|record| record.output_start.is_some())
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&CommandRecord> {
+        self.records
+            .iter()
+            .find(|record| record.index == index && record.output_start.is_some())
+    }
+
+    fn awaiting_command(&mut self) -> Option<&mut CommandRecord> {
+        self.records
+            .back_mut()
+            .filter(|record| record.output_start.is_none())
+    }
+
+    pub fn running(&self) -> Option<&CommandRecord> {
+        self.records
+            .back()
+            .filter(|record| record.output_start.is_some() && record.finished_at.is_none())
+    }
+
+    fn running_mut(&mut self) -> Option<&mut CommandRecord> {
+        self.records
+            .back_mut()
+            .filter(|record| record.output_start.is_some() && record.finished_at.is_none())
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, col: usize) -> Position {
+        Position { line, col }
+    }
+
+    #[test]
+    fn prompts_without_a_command_are_not_listed() {
+        let mut history = CommandHistory::new();
+        history.prompt_start(0, None);
+        history.command_start(at(0, 2));
+        history.prompt_start(1, None);
+        history.command_start(at(1, 2));
+        history.output_start(at(2, 0), "ls".to_string(), None);
+        history.finish("a b".to_string(), Some(0));
+
+        let records = history.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].index, 1);
+        assert_eq!(records[0].prompt_line, 1);
+        assert_eq!(records[0].command, "ls");
+        assert_eq!(records[0].exit_code, Some(0));
+        assert!(records[0].started_at <= records[0].finished_at);
+        assert_eq!(history.get(1).and_then(CommandRecord::output), Some("a b"));
+        assert!(history.get(0).is_none());
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut history = CommandHistory::new();
+        for line in 0..HISTORY_LIMIT + 5 {
+            history.prompt_start(line, None);
+            history.output_start(at(line, 0), format!("cmd{line}"), None);
+            history.finish(String::new(), None);
+        }
+        let records = history.records();
+        assert_eq!(records.len(), HISTORY_LIMIT);
+        assert_eq!(records[0].index, 5);
+    }
+}
@@ -2,14 +2,37 @@
 
 mod commands;
 mod config;
+mod foreground;
+mod history;
+mod notification;
+mod output;
+mod playback;
 mod pty;
+mod recording;
+mod redaction;
+mod restart;
+mod screen;
+mod shell_integration;
+mod shutdown;
+mod signal;
 mod state;
+mod title;
+mod transcript;
+mod utf8;
+mod workdir;
 
-use commands::{resize, start_session, stop_session, write_stdin};
-use tauri::Manager;
+use commands::{
+    ack_output, attach_session, close_window, control_playback, get_command_output,
+    get_foreground_process, get_session_cwd, get_terminal_state, list_commands, list_profiles,
+    resize, send_signal, start_playback, start_recording, start_session, stop_recording,
+    stop_session, write_stdin, write_stdin_bytes,
+};
+use foreground::CloseRequestedPayload;
+use tauri::{Emitter, Manager, WindowEvent};
 
 fn main() {
     tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
         .manage(state::AppState::new())
         .setup(|app| {
             let state = app.state::<state::AppState>();
@@ -23,11 +46,49 @@ fn main() {
 
             Ok(())
         })
+        .on_window_event(|window, event| match event {
+            WindowEvent::CloseRequested { api, .. } => {
+                let state = window.state::<state::AppState>();
+                let processes = state.session.busy_processes(window.label());
+                if processes.is_empty() {
+                    return;
+                }
+                // The webview asks the user and calls close_window to go ahead.
+                api.prevent_close();
+                let payload = CloseRequestedPayload { processes };
+                if let Err(err) = window.emit_to(window.label(), "window:close-requested", payload)
+                {
+                    eprintln!("failed to ask before closing {}: {err}", window.label());
+                }
+            }
+            WindowEvent::Destroyed => {
+                let state = window.state::<state::AppState>();
+                if let Err(err) = state.session.stop_window(window.label()) {
+                    eprintln!("failed to stop sessions for {}: {err}", window.label());
+                }
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             start_session,
+            list_profiles,
+            attach_session,
             write_stdin,
+            write_stdin_bytes,
             resize,
-            stop_session
+            send_signal,
+            stop_session,
+            get_foreground_process,
+            close_window,
+            ack_output,
+            get_terminal_state,
+            get_session_cwd,
+            list_commands,
+            get_command_output,
+            start_recording,
+            stop_recording,
+            start_playback,
+            control_playback
         ])
         .run(tauri::generate_context!())
         .expect("failed to run d3term");
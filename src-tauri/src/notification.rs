@@ -0,0 +1,125 @@
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{config::NotificationConfig, pty::SessionId, screen::TerminalEvent, state::AppState};
+
+pub fn notify_events(app: &AppHandle, session_id: SessionId, events: Vec<TerminalEvent>) {
+    if events.is_empty() {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let config = state.config.current().notifications;
+    if !config.enabled || window_focused(app, &state, session_id) {
+        return;
+    }
+    for event in &events {
+        if let Some((title, body)) = message(&config, event) {
+            let result = app.notification().builder().title(title).body(body).show();
+            if let Err(err) = result {
+                eprintln!("failed to show notification: {err}");
+            }
+        }
+    }
+}
+
+fn window_focused(app: &AppHandle, state: &AppState, session_id: SessionId) -> bool {
+    state
+        .session
+        .window_label(session_id)
+        .and_then(|label| app.get_webview_window(&label))
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false)
+}
+
+fn message(config: &NotificationConfig, event: &TerminalEvent) -> Option<(String, String)> {
+    match event {
+        TerminalEvent::CommandFinished {
+            command,
+            exit_code,
+            duration_ms,
+        } => {
+            if config.long_command_seconds == 0 || *duration_ms < config.long_command_seconds * 1000
+            {
+                return None;
+            }
+            let title = match exit_code {
+                Some(0) => "コマンドが完了しました".to_string(),
+                Some(code) => format!("コマンドが失敗しました (終了コード {code})"),
+                None => "コマンドが終了しました".to_string(),
+            };
+            let body = format!("{command}\n所要時間: {}", format_duration(*duration_ms));
+            Some((title, body))
+        }
+        TerminalEvent::Notification { title, body } => {
+            if !config.osc_notifications {
+                return None;
+            }
+            let title = title.clone().unwrap_or_else(|| "d3term".to_string());
+            Some((title, body.clone()))
+        }
+    }
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    let seconds = duration_ms / 1000;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}時間{minutes}分{seconds}秒")
+    } else if minutes > 0 {
+        format!("{minutes}分{seconds}秒")
+    } else {
+        format!("{seconds}秒")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(exit_code: Option<i32>, duration_ms: u64) -> TerminalEvent {
+        TerminalEvent::CommandFinished {
+            command: "cargo build".to_string(),
+            exit_code,
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn only_long_commands_are_notified() {
+        let config = NotificationConfig::default();
+        assert_eq!(message(&config, &finished(Some(0), 9_999)), None);
+        assert_eq!(
+            message(&config, &finished(Some(0), 75_000)),
+            Some((
+                "コマンドが完了しました".to_string(),
+                "cargo build\n所要時間: 1分15秒".to_string()
+            ))
+        );
+        let failed = message(&config, &finished(Some(101), 3_725_000)).expect("notified");
+        assert_eq!(failed.0, "コマンドが失敗しました (終了コード 101)");
+        assert!(failed.1.ends_with("1時間2分5秒"));
+
+        let disabled = NotificationConfig {
+            long_command_seconds: 0,
+            ..NotificationConfig::default()
+        };
+        assert_eq!(message(&disabled, &finished(Some(0), 75_000)), None);
+    }
+
+    #[test]
+    fn osc_notifications_can_be_turned_off() {
+        let event = TerminalEvent::Notification {
+            title: None,
+            body: "done".to_string(),
+        };
+        assert_eq!(
+            message(&NotificationConfig::default(), &event),
+            Some(("d3term".to_string(), "done".to_string()))
+        );
+        let config = NotificationConfig {
+            osc_notifications: false,
+            ..NotificationConfig::default()
+        };
+        assert_eq!(message(&config, &event), None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::config::PerformanceConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLimits {
+    pub batch_interval: Duration,
+    pub batch_max_bytes: usize,
+    pub high_water_mark: usize,
+}
+
+impl OutputLimits {
+    pub fn from_config(config: &PerformanceConfig) -> Self {
+        Self {
+            batch_interval: Duration::from_millis(config.output_batch_ms),
+            batch_max_bytes: config.output_batch_bytes.max(1),
+            high_water_mark: config.output_high_water_mark,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FlowState {
+    pending: Vec<u8>,
+    first_pending_at: Option<Instant>,
+    unacked: usize,
+    closed: bool,
+}
+
+#[derive(Debug)]
+pub struct OutputFlow {
+    limits: OutputLimits,
+    state: Mutex<FlowState>,
+    changed: Condvar,
+}
+
+impl OutputFlow {
+    pub fn new(limits: OutputLimits) -> Self {
+        Self {
+            limits,
+            state: Mutex::new(FlowState::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn wait_for_capacity(&self) -> bool {
+        let mut state = self.lock();
+        while !state.closed && self.is_saturated(&state) {
+            state = self
+                .changed
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        !state.closed
+    }
+
+    pub fn push(&self, bytes: &[u8]) {
+        let mut state = self.lock();
+        if state.closed || bytes.is_empty() {
+            return;
+        }
+        if state.first_pending_at.is_none() {
+            state.first_pending_at = Some(Instant::now());
+        }
+        state.pending.extend_from_slice(bytes);
+        self.changed.notify_all();
+    }
+
+    pub fn next_batch(&self) -> Option<Vec<u8>> {
+        let mut state = self.lock();
+        loop {
+            if let Some(first_pending_at) = state.first_pending_at {
+                let deadline = first_pending_at + self.limits.batch_interval;
+                let now = Instant::now();
+                if state.closed
+                    || now >= deadline
+                    || state.pending.len() >= self.limits.batch_max_bytes
+                {
+                    let batch = std::mem::take(&mut state.pending);
+                    state.first_pending_at = None;
+                    state.unacked += batch.len();
+                    return Some(batch);
+                }
+                state = self
+                    .changed
+                    .wait_timeout(state, deadline - now)
+                    .map(|(guard, _)| guard)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner().0);
+            } else if state.closed {
+                return None;
+            } else {
+                state = self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+        }
+    }
+
+    pub fn ack(&self, bytes: usize) {
+        let mut state = self.lock();
+        state.unacked = state.unacked.saturating_sub(bytes);
+        self.changed.notify_all();
+    }
+
+    pub fn reset_unacked(&self, bytes: usize) {
+        let mut state = self.lock();
+        state.unacked = bytes;
+        self.changed.notify_all();
+    }
+
+    pub fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        self.changed.notify_all();
+    }
+
+    fn is_saturated(&self, state: &FlowState) -> bool {
+        self.limits.high_water_mark > 0
+            && state.unacked + state.pending.len() >= self.limits.high_water_mark
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FlowState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayBuffer {
+    bytes: VecDeque<u8>,
+    limit: usize,
+    truncated: bool,
+}
+
+impl ReplayBuffer {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            bytes: VecDeque::new(),
+            limit,
+            truncated: false,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.limit == 0 {
+            return;
+        }
+        let overflow = (self.bytes.len() + bytes.len()).saturating_sub(self.limit);
+        if overflow > 0 {
+            self.truncated = true;
+        }
+        let dropped = overflow.min(self.bytes.len());
+        self.bytes.drain(..dropped);
+        self.bytes.extend(&bytes[overflow - dropped..]);
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        let mut start = 0;
+        if self.truncated {
+            // Start replay on a fresh line so a cut-off escape sequence or UTF-8 char is not shown.
+            if let Some(newline) = self.bytes.iter().position(|byte| *byte == b'\n') {
+                start = newline + 1;
+            }
+        }
+        self.bytes.range(start..).copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    fn limits(batch_max_bytes: usize, high_water_mark: usize) -> OutputLimits {
+        OutputLimits {
+            batch_interval: Duration::from_millis(20),
+            batch_max_bytes,
+            high_water_mark,
+        }
+    }
+
+    #[test]
+    fn small_reads_are_coalesced_into_one_batch() {
+        let flow = OutputFlow::new(limits(1024, 0));
+        flow.push(b"ab");
+        flow.push(b"cd");
+        flow.push(b"ef");
+        assert_eq!(flow.next_batch().as_deref(), Some(&b"abcdef"[..]));
+    }
+
+    #[test]
+    fn batch_is_flushed_early_when_size_limit_is_reached() {
+        let flow = OutputFlow::new(OutputLimits {
+            batch_interval: Duration::from_secs(60),
+            batch_max_bytes: 4,
+            high_water_mark: 0,
+        });
+        flow.push(b"abcd");
+        assert_eq!(flow.next_batch().as_deref(), Some(&b"abcd"[..]));
+    }
+
+    #[test]
+    fn close_flushes_remaining_bytes_then_ends() {
+        let flow = OutputFlow::new(limits(1024, 0));
+        flow.push(b"tail");
+        flow.close();
+        assert_eq!(flow.next_batch().as_deref(), Some(&b"tail"[..]));
+        assert_eq!(flow.next_batch(), None);
+    }
+
+    #[test]
+    fn reader_waits_until_consumed_bytes_are_acknowledged() {
+        let flow = Arc::new(OutputFlow::new(limits(1024, 8)));
+        flow.push(b"12345678");
+        assert_eq!(flow.next_batch().map(|batch| batch.len()), Some(8));
+
+        let waiter = {
+            let flow = Arc::clone(&flow);
+            thread::spawn(move || flow.wait_for_capacity())
+        };
+        thread::sleep(Duration::from_millis(30));
+        assert!(!waiter.is_finished());
+
+        flow.ack(8);
+        assert!(waiter.join().expect("waiter should finish"));
+    }
+
+    #[test]
+    fn replay_buffer_keeps_everything_below_the_limit() {
+        let mut replay = ReplayBuffer::new(16);
+        replay.push(b"abc");
+        replay.push(b"def");
+        assert_eq!(replay.contents(), b"abcdef");
+    }
+
+    #[test]
+    fn replay_buffer_drops_oldest_bytes_and_starts_on_a_new_line() {
+        let mut replay = ReplayBuffer::new(10);
+        replay.push(b"first\r\n");
+        replay.push(b"second\r\nthird");
+        assert_eq!(replay.contents(), b"third");
+
+        let mut replay = ReplayBuffer::new(4);
+        replay.push(b"0123456789");
+        assert_eq!(replay.contents(), b"6789");
+    }
+
+    #[test]
+    fn close_releases_a_blocked_reader() {
+        let flow = Arc::new(OutputFlow::new(limits(1024, 4)));
+        flow.push(b"1234");
+
+        let waiter = {
+            let flow = Arc::clone(&flow);
+            thread::spawn(move || flow.wait_for_capacity())
+        };
+        flow.close();
+        assert!(!waiter.join().expect("waiter should finish"));
+    }
+}
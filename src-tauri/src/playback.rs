@@ -0,0 +1,300 @@
+use std::{
+    sync::{Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 16.0;
+// Long gaps are waited out in slices so a huge timestamp cannot overflow a Duration.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CastEvent {
+    Output(String),
+    Resize(u16, u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub width: u16,
+    pub height: u16,
+    pub idle_time_limit: Option<f64>,
+    pub events: Vec<(f64, CastEvent)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CastHeader {
+    version: u8,
+    width: u16,
+    height: u16,
+    idle_time_limit: Option<f64>,
+}
+
+pub fn parse_cast(input: &str) -> Result<Cast, String> {
+    let mut lines = input.lines().filter(|line| !line.trim().is_empty());
+    let header = lines
+        .next()
+        .ok_or_else(|| "cast file is empty".to_string())?;
+    let header = serde_json::from_str::<CastHeader>(header)
+        .map_err(|err| format!("invalid cast header: {err}"))?;
+    if header.version != 2 {
+        return Err(format!("unsupported asciicast version: {}", header.version));
+    }
+
+    let mut events = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let (time, code, data) = serde_json::from_str::<(f64, String, Value)>(line)
+            .map_err(|err| format!("invalid cast event on line {}: {err}", index + 2))?;
+        let data = data.as_str().unwrap_or_default();
+        let event = match code.as_str() {
+            "o" => CastEvent::Output(data.to_string()),
+            "r" => match parse_size(data) {
+                Some((cols, rows)) => CastEvent::Resize(cols, rows),
+                None => continue,
+            },
+            _ => continue,
+        };
+        events.push((time, event));
+    }
+
+    Ok(Cast {
+        width: header.width,
+        height: header.height,
+        idle_time_limit: header.idle_time_limit,
+        events,
+    })
+}
+
+fn parse_size(value: &str) -> Option<(u16, u16)> {
+    let (cols, rows) = value.split_once('x')?;
+    Some((cols.trim().parse().ok()?, rows.trim().parse().ok()?))
+}
+
+#[derive(Debug)]
+struct ControlState {
+    paused: bool,
+    speed: f64,
+    stopped: bool,
+}
+
+#[derive(Debug)]
+pub struct PlaybackControl {
+    state: Mutex<ControlState>,
+    changed: Condvar,
+}
+
+impl PlaybackControl {
+    pub fn new(speed: f64) -> Self {
+        Self {
+            state: Mutex::new(ControlState {
+                paused: false,
+                speed: clamp_speed(speed),
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.lock().paused = paused;
+        self.changed.notify_all();
+    }
+
+    pub fn set_speed(&self, speed: f64) {
+        self.lock().speed = clamp_speed(speed);
+        self.changed.notify_all();
+    }
+
+    pub fn stop(&self) {
+        self.lock().stopped = true;
+        self.changed.notify_all();
+    }
+
+    fn wait(&self, recorded: f64) -> bool {
+        let mut remaining = recorded;
+        let mut state = self.lock();
+        loop {
+            if state.stopped {
+                return false;
+            }
+            if state.paused {
+                state = self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                continue;
+            }
+            if remaining <= 0.0 {
+                return true;
+            }
+
+            let speed = state.speed;
+            let started_at = Instant::now();
+            let timeout = Duration::try_from_secs_f64(remaining / speed)
+                .unwrap_or(MAX_WAIT)
+                .min(MAX_WAIT);
+            state = self
+                .changed
+                .wait_timeout(state, timeout)
+                .map(|(guard, _)| guard)
+                .unwrap_or_else(|poisoned| poisoned.into_inner().0);
+            remaining -= started_at.elapsed().as_secs_f64() * speed;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ControlState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn clamp_speed(speed: f64) -> f64 {
+    if speed.is_finite() {
+        speed.clamp(MIN_SPEED, MAX_SPEED)
+    } else {
+        1.0
+    }
+}
+
+pub fn play<F>(
+    cast: &Cast,
+    idle_time_limit: Option<f64>,
+    control: &PlaybackControl,
+    mut emit: F,
+) -> bool
+where
+    F: FnMut(&CastEvent),
+{
+    let idle_time_limit = idle_time_limit
+        .or(cast.idle_time_limit)
+        .filter(|limit| *limit > 0.0);
+    let mut previous = 0.0;
+    for (time, event) in &cast.events {
+        let mut delay = (time - previous).max(0.0);
+        previous = *time;
+        if let Some(limit) = idle_time_limit {
+            delay = delay.min(limit);
+        }
+        if !control.wait(delay) {
+            return false;
+        }
+        emit(event);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    const SAMPLE: &str = r#"{"version": 2, "width": 80, "height": 24, "idle_time_limit": 1.5}
+[0.1, "o", "$ ls\r\n"]
+[0.2, "i", "ls\r"]
+[0.5, "r", "100x30"]
+[0.6, "o", "日本語"]
+"#;
+
+    #[test]
+    fn parse_cast_reads_header_and_known_events() {
+        let cast = parse_cast(SAMPLE).expect("cast should parse");
+        assert_eq!((cast.width, cast.height), (80, 24));
+        assert_eq!(cast.idle_time_limit, Some(1.5));
+        assert_eq!(
+            cast.events,
+            vec![
+                (0.1, CastEvent::Output("$ ls\r\n".to_string())),
+                (0.5, CastEvent::Resize(100, 30)),
+                (0.6, CastEvent::Output("日本語".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_cast_rejects_other_versions_and_broken_events() {
+        assert!(parse_cast(r#"{"version": 1, "width": 80, "height": 24}"#).is_err());
+        assert!(parse_cast("").is_err());
+        let broken = "{\"version\": 2, \"width\": 80, \"height\": 24}\n[0.1, \"o\"\n";
+        assert!(parse_cast(broken).is_err());
+    }
+
+    #[test]
+    fn idle_time_limit_caps_long_gaps() {
+        let cast = Cast {
+            width: 80,
+            height: 24,
+            idle_time_limit: None,
+            events: vec![
+                (0.0, CastEvent::Output("a".to_string())),
+                (3600.0, CastEvent::Output("b".to_string())),
+            ],
+        };
+        let control = PlaybackControl::new(1.0);
+        let started_at = Instant::now();
+        let mut output = String::new();
+        let completed = play(&cast, Some(0.01), &control, |event| {
+            if let CastEvent::Output(data) = event {
+                output.push_str(data);
+            }
+        });
+        assert!(completed);
+        assert_eq!(output, "ab");
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn huge_gaps_wait_without_panicking() {
+        let cast = Cast {
+            width: 80,
+            height: 24,
+            idle_time_limit: None,
+            events: vec![(1e300, CastEvent::Output("a".to_string()))],
+        };
+        let control = Arc::new(PlaybackControl::new(MIN_SPEED));
+        let player = {
+            let control = Arc::clone(&control);
+            thread::spawn(move || play(&cast, None, &control, |_| {}))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!player.is_finished());
+
+        control.stop();
+        assert!(!player.join().expect("player should not panic"));
+    }
+
+    #[test]
+    fn stopped_playback_emits_nothing() {
+        let cast = parse_cast(SAMPLE).expect("cast should parse");
+        let control = PlaybackControl::new(1.0);
+        control.stop();
+        let mut emitted = 0;
+        assert!(!play(&cast, None, &control, |_| emitted += 1));
+        assert_eq!(emitted, 0);
+    }
+
+    #[test]
+    fn paused_playback_resumes_when_unpaused() {
+        let cast = parse_cast(SAMPLE).expect("cast should parse");
+        let control = Arc::new(PlaybackControl::new(MAX_SPEED));
+        control.set_paused(true);
+
+        let player = {
+            let control = Arc::clone(&control);
+            thread::spawn(move || {
+                let mut emitted = 0;
+                let completed = play(&cast, None, &control, |_| emitted += 1);
+                (completed, emitted)
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!player.is_finished());
+
+        control.set_paused(false);
+        assert_eq!(player.join().expect("player should finish"), (true, 3));
+    }
+}
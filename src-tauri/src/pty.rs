@@ -1,31 +1,88 @@
 use std::{
+    collections::HashMap,
     env,
-    io::{Read, Write},
-    path::Path,
-    sync::Mutex,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
+use chrono::Local;
 use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
 use serde::Serialize;
-use tauri::{AppHandle, Emitter};
+use tauri::{
+    ipc::{Channel, InvokeResponseBody},
+    AppHandle, Emitter, Manager,
+};
+
+#[cfg(unix)]
+use crate::signal::{parse_signal, send_to_group};
+use crate::{
+    config::{
+        resolve_state_dir, AppConfig, LoggingConfig, MultiplexerMode, RecordingConfig,
+        RedactionConfig, ShutdownConfig, StartupConfig,
+    },
+    foreground::ForegroundProcess,
+    history::CommandRecord,
+    notification::notify_events,
+    output::{OutputFlow, OutputLimits, ReplayBuffer},
+    playback::{parse_cast, play, CastEvent, PlaybackControl},
+    recording::{recording_file_name, resolve_recording_dir, AsciicastHeader, AsciicastWriter},
+    redaction::{CaptureFilter, EchoProbe},
+    restart::{close_session_window, exit_action, ExitAction},
+    screen::{ScreenSnapshot, TerminalModel},
+    shell_integration::{self, integration_dir, Injection, ShellKind},
+    shutdown::{stop_process, ChildExit, ExitWatch, ProcessEnd},
+    state::AppState,
+    title::{update_window_title, TitleTracker},
+    transcript::{render_file_name, resolve_log_dir, TranscriptLog},
+    workdir::{resolve_cwd, StartCwd},
+};
 
-use crate::config::{MultiplexerMode, StartupConfig};
+pub type SessionId = u64;
+
+const INPUT_QUEUE_CAPACITY: usize = 256;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct SessionInfo {
+    pub session_id: SessionId,
+    pub generation: u64,
     pub pid: Option<u32>,
     pub command: String,
+    pub profile: Option<String>,
     pub fallback_used: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct PtyDataPayload {
-    pub data: String,
+pub struct SessionExitPayload {
+    pub session_id: SessionId,
+    pub generation: u64,
+    pub code: Option<i32>,
+    pub signal: Option<String>,
+    pub killed_by_us: bool,
+    pub runtime_ms: u64,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct SessionExitPayload {
-    pub code: Option<i32>,
+pub struct PlaybackInfo {
+    #[serde(flatten)]
+    pub info: SessionInfo,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackResizePayload {
+    pub session_id: SessionId,
+    pub generation: u64,
+    pub cols: u16,
+    pub rows: u16,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -40,33 +97,232 @@ pub fn emit_warning(app: &AppHandle, message: impl Into<String>) {
     let _ = app.emit("warning", payload);
 }
 
+#[derive(Debug, Clone)]
+pub struct StartOptions {
+    pub session_id: Option<SessionId>,
+    pub window: String,
+    pub cols: u16,
+    pub rows: u16,
+    pub profile: Option<String>,
+    pub cwd: Option<StartCwd>,
+    pub restarts: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaybackOptions {
+    pub path: PathBuf,
+    pub window: String,
+    pub speed: f64,
+    pub idle_time_limit: Option<f64>,
+}
+
+struct OutputSink {
+    channel: Channel,
+    replay: ReplayBuffer,
+}
+
+struct ActiveRecording {
+    path: PathBuf,
+    writer: AsciicastWriter<BufWriter<File>>,
+}
+
+impl ActiveRecording {
+    fn finish(self) -> io::Result<PathBuf> {
+        self.writer.finish()?;
+        Ok(self.path)
+    }
+}
+
+struct Capture {
+    filter: CaptureFilter,
+    echo: EchoProbe,
+    transcript: Option<TranscriptLog>,
+    recording: Arc<Mutex<Option<ActiveRecording>>>,
+}
+
+impl Capture {
+    fn write(&mut self, app: &AppHandle, bytes: &[u8]) {
+        let recording_active = self
+            .recording
+            .lock()
+            .map(|recording| recording.is_some())
+            .unwrap_or(false);
+        if self.transcript.is_none() && !recording_active {
+            return;
+        }
+        let captured = self.filter.filter(bytes, self.echo.input_hidden());
+        self.persist(app, &captured);
+    }
+
+    fn finish(&mut self, app: &AppHandle) {
+        let captured = self.filter.finish();
+        self.persist(app, &captured);
+    }
+
+    fn persist(&mut self, app: &AppHandle, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if let Some(log) = self.transcript.as_mut() {
+            if let Err(err) = log.write(bytes) {
+                self.transcript = None;
+                emit_warning(
+                    app,
+                    format!("セッションログの書き込みに失敗したため停止しました: {err}"),
+                );
+            }
+        }
+        if let Ok(mut recording) = self.recording.lock() {
+            if let Some(active) = recording.as_mut() {
+                if let Err(err) = active.writer.write_output(bytes) {
+                    *recording = None;
+                    emit_warning(
+                        app,
+                        format!("録画の書き込みに失敗したため停止しました: {err}"),
+                    );
+                }
+            }
+        }
+    }
+}
+
 struct PtySession {
+    info: SessionInfo,
+    window: String,
+    size: (u16, u16),
+    recording: Arc<Mutex<Option<ActiveRecording>>>,
+    active: Arc<AtomicBool>,
+    replaced: Arc<AtomicBool>,
+    output: Arc<OutputFlow>,
+    sink: Arc<Mutex<OutputSink>>,
+    model: Arc<Mutex<TerminalModel>>,
     master: Box<dyn MasterPty + Send>,
-    writer: Box<dyn Write + Send>,
+    input: SyncSender<Vec<u8>>,
     killer: Box<dyn ChildKiller + Send + Sync>,
+    exit: Arc<ExitWatch>,
+    shutdown: ShutdownConfig,
+    cwd: Option<String>,
+}
+
+struct ReplaySession {
+    info: SessionInfo,
+    window: String,
+    model: Arc<Mutex<TerminalModel>>,
+    control: Arc<PlaybackControl>,
+}
+
+enum Session {
+    Pty(PtySession),
+    Replay(ReplaySession),
+}
+
+impl Session {
+    fn window(&self) -> &str {
+        match self {
+            Session::Pty(session) => &session.window,
+            Session::Replay(session) => &session.window,
+        }
+    }
+
+    fn model(&self) -> &Arc<Mutex<TerminalModel>> {
+        match self {
+            Session::Pty(session) => &session.model,
+            Session::Replay(session) => &session.model,
+        }
+    }
+
+    fn as_pty(&self) -> Result<&PtySession, String> {
+        match self {
+            Session::Pty(session) => Ok(session),
+            Session::Replay(session) => Err(format!(
+                "session {} is a playback session",
+                session.info.session_id
+            )),
+        }
+    }
+
+    fn as_pty_mut(&mut self) -> Result<&mut PtySession, String> {
+        match self {
+            Session::Pty(session) => Ok(session),
+            Session::Replay(session) => Err(format!(
+                "session {} is a playback session",
+                session.info.session_id
+            )),
+        }
+    }
+
+    fn shutdown(self) -> Option<ProcessEnd> {
+        match self {
+            Session::Pty(session) => Some(shutdown_session(session)),
+            Session::Replay(session) => {
+                session.control.stop();
+                None
+            }
+        }
+    }
+
+    fn shutdown_in_background(self) {
+        std::thread::spawn(move || {
+            self.shutdown();
+        });
+    }
 }
 
 pub struct SessionManager {
-    inner: Mutex<Option<PtySession>>,
+    sessions: Mutex<HashMap<SessionId, Session>>,
+    next_id: AtomicU64,
+    next_generation: AtomicU64,
 }
 
 impl SessionManager {
     pub fn new() -> Self {
         Self {
-            inner: Mutex::new(None),
+            sessions: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            next_generation: AtomicU64::new(1),
         }
     }
 
     pub fn start(
         &self,
         app: &AppHandle,
-        startup: &StartupConfig,
-        cols: u16,
-        rows: u16,
+        config: &AppConfig,
+        options: StartOptions,
+        on_data: Channel,
     ) -> Result<SessionInfo, String> {
-        self.stop()?;
+        let StartOptions {
+            session_id,
+            window,
+            cols,
+            rows,
+            profile,
+            cwd,
+            restarts,
+        } = options;
+        let selected = config.select_profile(profile.as_deref())?;
+        if let Some(message) = selected.warning.as_deref() {
+            emit_warning(app, message);
+        }
+        // A session that is gone or has not reported its directory yet falls back to the profile.
+        let cwd = match cwd {
+            Some(StartCwd::Path(path)) => Some(path),
+            Some(StartCwd::Session(source)) => self.session_cwd(source).ok().flatten(),
+            None => None,
+        };
+        let (launch_dir, cwd_warning) =
+            resolve_cwd(cwd.as_deref().or(selected.startup.cwd.as_deref()), |name| {
+                env::var(name).ok()
+            });
+        if let Some(message) = cwd_warning.as_deref() {
+            emit_warning(app, message);
+        }
+        // A session being replaced stays in place until its successor has spawned, so a failed
+        // start (or restart) leaves the window with the session it had.
+        let session_id = session_id.unwrap_or_else(|| self.next_id.fetch_add(1, Ordering::Relaxed));
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let active = Arc::new(AtomicBool::new(true));
 
-        let resolved = resolve_startup_command(startup)?;
+        let resolved = resolve_startup_command(&selected.startup)?;
         if let Some(message) = resolved.warning.as_deref() {
             emit_warning(app, message);
         }
@@ -81,13 +337,46 @@ impl SessionManager {
             })
             .map_err(|err| format!("failed to open PTY: {err}"))?;
 
+        let (args, integration_env) = match resolved.integration {
+            Some(injection) => match shell_integration::install(&integration_dir()) {
+                Ok(()) => (injection.args, injection.env),
+                Err(err) => {
+                    emit_warning(
+                        app,
+                        format!("シェル統合を準備できないため無効にします: {err}"),
+                    );
+                    (resolved.args, Vec::new())
+                }
+            },
+            None => (resolved.args, Vec::new()),
+        };
         let mut command = CommandBuilder::new(&resolved.program);
-        for arg in &resolved.args {
+        for arg in &args {
             command.arg(arg);
         }
         command.env("TERM", "xterm-256color");
         command.env("COLORTERM", "truecolor");
+        if let Some(launch_dir) = &launch_dir {
+            command.cwd(launch_dir);
+        }
+        for (key, value) in &selected.startup.env {
+            command.env(key, value);
+        }
+        // Shell integration variables go last so a profile cannot break the injection.
+        for (key, value) in &integration_env {
+            command.env(key, value);
+        }
 
+        // The PTY handles are taken before spawning, so failing to get them never leaves a child
+        // running without a session.
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|err| format!("failed to clone PTY reader: {err}"))?;
+        let mut writer = pair
+            .master
+            .take_writer()
+            .map_err(|err| format!("failed to take PTY writer: {err}"))?;
         let mut child = pair
             .slave
             .spawn_command(command)
@@ -95,111 +384,887 @@ impl SessionManager {
         let pid = child.process_id();
         let killer = child.clone_killer();
 
-        let mut reader = pair
-            .master
-            .try_clone_reader()
-            .map_err(|err| format!("failed to clone PTY reader: {err}"))?;
+        let output = Arc::new(OutputFlow::new(OutputLimits::from_config(
+            &config.performance,
+        )));
+        let model = Arc::new(Mutex::new(TerminalModel::new(
+            cols.max(2),
+            rows.max(1),
+            config.terminal.scrollback as usize,
+        )));
+        let recording = Arc::new(Mutex::new(None::<ActiveRecording>));
+        let mut capture = Capture {
+            filter: open_capture_filter(app, &config.redaction),
+            echo: EchoProbe::new(pair.master.as_ref()),
+            transcript: open_transcript(app, &config.logging, session_id, &resolved.display),
+            recording: Arc::clone(&recording),
+        };
         let app_for_reader = app.clone();
-        std::thread::spawn(move || {
+        let active_for_reader = Arc::clone(&active);
+        let output_for_reader = Arc::clone(&output);
+        let model_for_reader = Arc::clone(&model);
+        let command_for_reader = resolved.display.clone();
+        let read_output = move || {
             let mut buffer = [0_u8; 8192];
-            loop {
+            let mut title_tracker = TitleTracker::default();
+            while output_for_reader.wait_for_capacity() {
                 match reader.read(&mut buffer) {
                     Ok(0) => break,
                     Ok(size) => {
-                        let data = String::from_utf8_lossy(&buffer[..size]).to_string();
-                        let _ = app_for_reader.emit("pty:data", PtyDataPayload { data });
+                        if !active_for_reader.load(Ordering::Acquire) {
+                            break;
+                        }
+                        let (events, title_change) = match model_for_reader.lock() {
+                            Ok(mut model) => {
+                                model.advance(&buffer[..size]);
+                                let title_change =
+                                    title_tracker.changed(model.title(), model.cwd()).then(|| {
+                                        (model.title().to_string(), model.cwd().map(str::to_string))
+                                    });
+                                (model.take_events(), title_change)
+                            }
+                            Err(_) => (Vec::new(), None),
+                        };
+                        if let Some((title, cwd)) = title_change {
+                            update_window_title(
+                                &app_for_reader,
+                                session_id,
+                                &command_for_reader,
+                                &title,
+                                cwd.as_deref(),
+                            );
+                        }
+                        notify_events(&app_for_reader, session_id, events);
+                        capture.write(&app_for_reader, &buffer[..size]);
+                        output_for_reader.push(&buffer[..size]);
                     }
                     Err(_) => break,
                 }
             }
+            capture.finish(&app_for_reader);
+            output_for_reader.close();
+        };
+
+        let sink = Arc::new(Mutex::new(OutputSink {
+            channel: on_data,
+            replay: ReplayBuffer::new(config.performance.replay_buffer_bytes),
+        }));
+        let active_for_sender = Arc::clone(&active);
+        let output_for_sender = Arc::clone(&output);
+        let sink_for_sender = Arc::clone(&sink);
+        std::thread::spawn(move || {
+            while let Some(batch) = output_for_sender.next_batch() {
+                if !active_for_sender.load(Ordering::Acquire) {
+                    break;
+                }
+                let Ok(mut sink) = sink_for_sender.lock() else {
+                    break;
+                };
+                sink.replay.push(&batch);
+                // A failed send usually means the webview is reloading; keep the session
+                // alive so the next attach_session can replay from the buffer.
+                let _ = sink.channel.send(InvokeResponseBody::Raw(batch));
+            }
         });
 
+        let exit = Arc::new(ExitWatch::default());
+        let replaced = Arc::new(AtomicBool::new(false));
         let app_for_exit = app.clone();
+        let active_for_exit = Arc::clone(&active);
+        let replaced_for_exit = Arc::clone(&replaced);
+        let exit_for_waiter = Arc::clone(&exit);
+        let started_at = Instant::now();
+        let wait_for_exit = move || {
+            let child_exit = ChildExit::from_wait(child.wait());
+            exit_for_waiter.set(child_exit.clone());
+            let ChildExit {
+                code,
+                signal,
+                error,
+            } = child_exit;
+            // The session id now belongs to a newer generation; its exit is not news to anyone.
+            if replaced_for_exit.load(Ordering::Acquire) {
+                return;
+            }
+            // stop() clears the flag before signalling, so a cleared flag means we ended it.
+            let killed_by_us = !active_for_exit.load(Ordering::Acquire);
+            let payload = SessionExitPayload {
+                session_id,
+                generation,
+                code,
+                signal,
+                killed_by_us,
+                runtime_ms: started_at.elapsed().as_millis() as u64,
+                error,
+            };
+            let _ = app_for_exit.emit("session:exit", payload);
+            if killed_by_us {
+                return;
+            }
+
+            let state = app_for_exit.state::<AppState>();
+            let on_exit = state.config.current().startup.on_exit;
+            match exit_action(on_exit, code, restarts, started_at.elapsed()) {
+                ExitAction::Hold => {}
+                ExitAction::Close => close_session_window(&app_for_exit, session_id),
+                ExitAction::Restart { delay, restarts } => {
+                    std::thread::sleep(delay);
+                    if !active_for_exit.load(Ordering::Acquire) {
+                        return;
+                    }
+                    let config = state.config.current();
+                    match state.session.restart(
+                        &app_for_exit,
+                        &config,
+                        session_id,
+                        generation,
+                        restarts,
+                    ) {
+                        Ok(Some(info)) => {
+                            let _ = app_for_exit.emit("session:restarted", info);
+                        }
+                        Ok(None) => {}
+                        Err(err) => emit_warning(
+                            &app_for_exit,
+                            format!("セッションを再起動できませんでした: {err}"),
+                        ),
+                    }
+                }
+            }
+        };
+
+        let (input, input_rx) = mpsc::sync_channel::<Vec<u8>>(INPUT_QUEUE_CAPACITY);
+        let app_for_writer = app.clone();
+        let active_for_writer = Arc::clone(&active);
         std::thread::spawn(move || {
-            let code = child.wait().ok().map(|status| status.exit_code() as i32);
-            let _ = app_for_exit.emit("session:exit", SessionExitPayload { code });
+            for chunk in input_rx {
+                let result = writer.write_all(&chunk).and_then(|_| writer.flush());
+                if let Err(err) = result {
+                    if active_for_writer.load(Ordering::Acquire) {
+                        emit_warning(
+                            &app_for_writer,
+                            format!("PTY への書き込みに失敗しました: {err}"),
+                        );
+                    }
+                    break;
+                }
+            }
         });
 
-        let writer = pair
-            .master
-            .take_writer()
-            .map_err(|err| format!("failed to take PTY writer: {err}"))?;
-
+        let info = SessionInfo {
+            session_id,
+            generation,
+            pid,
+            command: resolved.display,
+            profile: selected.name,
+            fallback_used: resolved.fallback_used,
+        };
         let session = PtySession {
+            info: info.clone(),
+            window,
+            size: (cols.max(2), rows.max(1)),
+            recording,
+            active,
+            replaced,
+            output,
+            sink,
+            model,
             master: pair.master,
-            writer,
+            input,
             killer,
+            exit,
+            shutdown: config.shutdown.clone(),
+            cwd,
         };
 
         let mut guard = self
-            .inner
+            .sessions
             .lock()
             .map_err(|_| "failed to lock session state".to_string())?;
-        *guard = Some(session);
+        let previous = guard.insert(session_id, Session::Pty(session));
+        drop(guard);
+        if let Some(previous) = previous {
+            if let Session::Pty(previous) = &previous {
+                previous.replaced.store(true, Ordering::Release);
+            }
+            previous.shutdown_in_background();
+        }
+        // Reading and waiting start only once the session is in the map, so a title set by the
+        // very first output, or an immediate exit, can already find its window.
+        std::thread::spawn(read_output);
+        std::thread::spawn(wait_for_exit);
 
-        Ok(SessionInfo {
-            pid,
-            command: resolved.display,
-            fallback_used: resolved.fallback_used,
-        })
+        Ok(info)
+    }
+
+    pub fn restart(
+        &self,
+        app: &AppHandle,
+        config: &AppConfig,
+        session_id: SessionId,
+        generation: u64,
+        restarts: u32,
+    ) -> Result<Option<SessionInfo>, String> {
+        let (window, (cols, rows), profile, cwd, on_data) = {
+            let guard = self
+                .sessions
+                .lock()
+                .map_err(|_| "failed to lock session state".to_string())?;
+            // The session may have been stopped or replaced while waiting for the backoff.
+            let Some(Session::Pty(session)) = guard.get(&session_id) else {
+                return Ok(None);
+            };
+            if session.info.generation != generation {
+                return Ok(None);
+            }
+            let channel = session
+                .sink
+                .lock()
+                .map_err(|_| "failed to lock session output".to_string())?
+                .channel
+                .clone();
+            (
+                session.window.clone(),
+                session.size,
+                session.info.profile.clone(),
+                session.cwd.clone(),
+                channel,
+            )
+        };
+        let options = StartOptions {
+            session_id: Some(session_id),
+            window,
+            cols,
+            rows,
+            profile,
+            cwd: cwd.map(StartCwd::Path),
+            restarts,
+        };
+        self.start(app, config, options, on_data).map(Some)
     }
 
-    pub fn write_stdin(&self, data: String) -> Result<(), String> {
+    pub fn start_playback(
+        &self,
+        app: &AppHandle,
+        config: &AppConfig,
+        options: PlaybackOptions,
+        on_data: Channel,
+    ) -> Result<PlaybackInfo, String> {
+        let PlaybackOptions {
+            path,
+            window,
+            speed,
+            idle_time_limit,
+        } = options;
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        let cast = parse_cast(&contents)
+            .map_err(|err| format!("failed to parse {}: {err}", path.display()))?;
+
+        let session_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let (cols, rows) = (cast.width.max(2), cast.height.max(1));
+        let model = Arc::new(Mutex::new(TerminalModel::new(
+            cols,
+            rows,
+            config.terminal.scrollback as usize,
+        )));
+        let control = Arc::new(PlaybackControl::new(speed));
+
+        let info = SessionInfo {
+            session_id,
+            generation,
+            pid: None,
+            command: path.display().to_string(),
+            profile: None,
+            fallback_used: false,
+        };
+        let session = ReplaySession {
+            info: info.clone(),
+            window,
+            model: Arc::clone(&model),
+            control: Arc::clone(&control),
+        };
+
+        // Register before the player starts so a short cast cannot end before it is in the map.
         let mut guard = self
-            .inner
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        guard.insert(session_id, Session::Replay(session));
+        drop(guard);
+
+        let app_for_player = app.clone();
+        let model_for_player = Arc::clone(&model);
+        let control_for_player = Arc::clone(&control);
+        let started_at = Instant::now();
+        std::thread::spawn(move || {
+            let completed = play(
+                &cast,
+                idle_time_limit,
+                &control_for_player,
+                |event| match event {
+                    CastEvent::Output(data) => {
+                        if let Ok(mut model) = model_for_player.lock() {
+                            model.advance(data.as_bytes());
+                        }
+                        let _ = on_data.send(InvokeResponseBody::Raw(data.as_bytes().to_vec()));
+                    }
+                    CastEvent::Resize(cols, rows) => {
+                        let (cols, rows) = ((*cols).max(2), (*rows).max(1));
+                        if let Ok(mut model) = model_for_player.lock() {
+                            model.resize(cols, rows);
+                        }
+                        let payload = PlaybackResizePayload {
+                            session_id,
+                            generation,
+                            cols,
+                            rows,
+                        };
+                        let _ = app_for_player.emit("playback:resize", payload);
+                    }
+                },
+            );
+            if completed {
+                let payload = SessionExitPayload {
+                    session_id,
+                    generation,
+                    code: None,
+                    signal: None,
+                    killed_by_us: false,
+                    runtime_ms: started_at.elapsed().as_millis() as u64,
+                    error: None,
+                };
+                let _ = app_for_player.emit("session:exit", payload);
+            }
+            let state = app_for_player.state::<AppState>();
+            state.session.remove_replay(session_id, generation);
+        });
+
+        Ok(PlaybackInfo { info, cols, rows })
+    }
+
+    fn remove_replay(&self, session_id: SessionId, generation: u64) {
+        let Ok(mut guard) = self.sessions.lock() else {
+            return;
+        };
+        let finished = matches!(
+            guard.get(&session_id),
+            Some(Session::Replay(session)) if session.info.generation == generation
+        );
+        if finished {
+            guard.remove(&session_id);
+        }
+    }
+
+    pub fn control_playback(
+        &self,
+        session_id: SessionId,
+        paused: Option<bool>,
+        speed: Option<f64>,
+    ) -> Result<(), String> {
+        let guard = self
+            .sessions
             .lock()
             .map_err(|_| "failed to lock session state".to_string())?;
         let session = guard
-            .as_mut()
-            .ok_or_else(|| "session is not running".to_string())?;
-
-        session
-            .writer
-            .write_all(data.as_bytes())
-            .map_err(|err| format!("failed to write to PTY: {err}"))?;
-        session
-            .writer
-            .flush()
-            .map_err(|err| format!("failed to flush PTY writer: {err}"))?;
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        let Session::Replay(session) = session else {
+            return Err(format!("session {session_id} is not a playback session"));
+        };
+        if let Some(paused) = paused {
+            session.control.set_paused(paused);
+        }
+        if let Some(speed) = speed {
+            session.control.set_speed(speed);
+        }
         Ok(())
     }
 
-    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+    pub fn attach(
+        &self,
+        session_id: SessionId,
+        window: String,
+        cols: u16,
+        rows: u16,
+        on_data: Channel,
+    ) -> Result<SessionInfo, String> {
         let mut guard = self
-            .inner
+            .sessions
             .lock()
             .map_err(|_| "failed to lock session state".to_string())?;
         let session = guard
-            .as_mut()
-            .ok_or_else(|| "session is not running".to_string())?;
-        session
-            .master
-            .resize(PtySize {
-                cols: cols.max(2),
-                rows: rows.max(1),
-                pixel_width: 0,
-                pixel_height: 0,
-            })
-            .map_err(|err| format!("failed to resize PTY: {err}"))
+            .get_mut(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?
+            .as_pty_mut()?;
+        session.window = window;
+        resize_session(session, cols, rows)?;
+
+        let mut sink = session
+            .sink
+            .lock()
+            .map_err(|_| "failed to lock session output".to_string())?;
+        let replay = sink.replay.contents();
+        session.output.reset_unacked(replay.len());
+        if !replay.is_empty() {
+            on_data
+                .send(InvokeResponseBody::Raw(replay))
+                .map_err(|err| format!("failed to replay session output: {err}"))?;
+        }
+        sink.channel = on_data;
+
+        Ok(session.info.clone())
     }
 
-    pub fn stop(&self) -> Result<(), String> {
+    pub fn write_stdin(&self, session_id: SessionId, data: Vec<u8>) -> Result<(), String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?
+            .as_pty()?;
+
+        match session.input.try_send(data) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                Err(format!("input queue for session {session_id} is full"))
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                Err(format!("input for session {session_id} is closed"))
+            }
+        }
+    }
+
+    pub fn resize(&self, session_id: SessionId, cols: u16, rows: u16) -> Result<(), String> {
         let mut guard = self
-            .inner
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get_mut(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        match session {
+            Session::Pty(session) => resize_session(session, cols, rows),
+            // A replay follows the recorded terminal size instead of the window.
+            Session::Replay(_) => Ok(()),
+        }
+    }
+
+    pub fn terminal_state(
+        &self,
+        session_id: SessionId,
+        include_scrollback: bool,
+    ) -> Result<ScreenSnapshot, String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        let model = session
+            .model()
+            .lock()
+            .map_err(|_| "failed to lock terminal state".to_string())?;
+        Ok(model.snapshot(include_scrollback))
+    }
+
+    pub fn list_commands(&self, session_id: SessionId) -> Result<Vec<CommandRecord>, String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        let model = session
+            .model()
+            .lock()
+            .map_err(|_| "failed to lock terminal state".to_string())?;
+        Ok(model.commands())
+    }
+
+    pub fn command_output(&self, session_id: SessionId, index: usize) -> Result<String, String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        let model = session
+            .model()
+            .lock()
+            .map_err(|_| "failed to lock terminal state".to_string())?;
+        model
+            .command_output(index)
+            .ok_or_else(|| format!("command {index} is not in the history of session {session_id}"))
+    }
+
+    pub fn session_cwd(&self, session_id: SessionId) -> Result<Option<String>, String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        let reported = session
+            .model()
+            .lock()
+            .map_err(|_| "failed to lock terminal state".to_string())?
+            .cwd()
+            .map(str::to_string);
+        if reported.is_some() {
+            return Ok(reported);
+        }
+        match session {
+            // The group leader may already be gone while others in its group still run.
+            Session::Pty(session) => Ok(foreground_pid(session)
+                .and_then(process_cwd)
+                .or_else(|| session.info.pid.and_then(process_cwd))),
+            Session::Replay(_) => Ok(None),
+        }
+    }
+
+    pub fn foreground_process(
+        &self,
+        session_id: SessionId,
+    ) -> Result<Option<ForegroundProcess>, String> {
+        let guard = self
+            .sessions
             .lock()
             .map_err(|_| "failed to lock session state".to_string())?;
-        if let Some(mut session) = guard.take() {
-            let _ = session.killer.kill();
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?;
+        match session {
+            Session::Pty(session) => Ok(foreground_process(session)),
+            Session::Replay(_) => Ok(None),
+        }
+    }
+
+    pub fn busy_processes(&self, window: &str) -> Vec<ForegroundProcess> {
+        let Ok(guard) = self.sessions.lock() else {
+            return Vec::new();
+        };
+        guard
+            .values()
+            .filter(|session| session.window() == window)
+            .filter_map(|session| session.as_pty().ok())
+            .filter_map(busy_foreground)
+            .collect()
+    }
+
+    pub fn send_signal(&self, session_id: SessionId, signal: &str) -> Result<u32, String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?
+            .as_pty()?;
+        signal_foreground_group(session, signal)
+    }
+
+    pub fn window_label(&self, session_id: SessionId) -> Option<String> {
+        let guard = self.sessions.lock().ok()?;
+        guard
+            .get(&session_id)
+            .map(|session| session.window().to_string())
+    }
+
+    pub fn ack_output(&self, session_id: SessionId, bytes: usize) -> Result<(), String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        if let Some(Session::Pty(session)) = guard.get(&session_id) {
+            session.output.ack(bytes);
+        }
+        Ok(())
+    }
+
+    pub fn start_recording(
+        &self,
+        config: &RecordingConfig,
+        session_id: SessionId,
+    ) -> Result<String, String> {
+        if !config.enabled {
+            return Err("recording is disabled; set [recording] enabled = true".to_string());
+        }
+
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?
+            .as_pty()?;
+        let mut recording = session
+            .recording
+            .lock()
+            .map_err(|_| "failed to lock recording state".to_string())?;
+        if let Some(active) = recording.as_ref() {
+            return Err(format!(
+                "session {session_id} is already being recorded to {}",
+                active.path.display()
+            ));
+        }
+
+        let (cols, rows) = session.size;
+        let header = AsciicastHeader::new(cols, rows, Some(session.info.command.clone()));
+        let path = resolve_recording_dir(&resolve_state_dir(), &config.directory)
+            .join(recording_file_name(session_id, header.timestamp));
+        let writer = AsciicastWriter::create(&path, &header)
+            .map_err(|err| format!("failed to create {}: {err}", path.display()))?;
+        let display = path.display().to_string();
+        *recording = Some(ActiveRecording { path, writer });
+        Ok(display)
+    }
+
+    pub fn stop_recording(&self, session_id: SessionId) -> Result<Option<String>, String> {
+        let guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session = guard
+            .get(&session_id)
+            .ok_or_else(|| format!("session {session_id} is not running"))?
+            .as_pty()?;
+        let active = session
+            .recording
+            .lock()
+            .map_err(|_| "failed to lock recording state".to_string())?
+            .take();
+        match active {
+            Some(active) => active
+                .finish()
+                .map(|path| Some(path.display().to_string()))
+                .map_err(|err| format!("failed to finish recording: {err}")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn stop(&self, session_id: SessionId, force: bool) -> Result<Option<ProcessEnd>, String> {
+        let mut guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        if !force {
+            if let Some(busy) = guard
+                .get(&session_id)
+                .and_then(|session| session.as_pty().ok())
+                .and_then(busy_foreground)
+            {
+                return Err(format!(
+                    "session {session_id} is running {} (process group {}); pass force to stop it",
+                    busy.name.as_deref().unwrap_or("a process"),
+                    busy.pgid
+                ));
+            }
+        }
+        let session = guard.remove(&session_id);
+        drop(guard);
+        Ok(session.and_then(Session::shutdown))
+    }
+
+    pub fn stop_window(&self, window: &str) -> Result<(), String> {
+        let mut guard = self
+            .sessions
+            .lock()
+            .map_err(|_| "failed to lock session state".to_string())?;
+        let session_ids = guard
+            .iter()
+            .filter(|(_, session)| session.window() == window)
+            .map(|(session_id, _)| *session_id)
+            .collect::<Vec<_>>();
+        for session_id in session_ids {
+            if let Some(session) = guard.remove(&session_id) {
+                session.shutdown_in_background();
+            }
         }
         Ok(())
     }
 }
 
+fn resize_session(session: &mut PtySession, cols: u16, rows: u16) -> Result<(), String> {
+    let (cols, rows) = (cols.max(2), rows.max(1));
+    session
+        .master
+        .resize(PtySize {
+            cols,
+            rows,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|err| format!("failed to resize PTY: {err}"))?;
+    if let Ok(mut model) = session.model.lock() {
+        model.resize(cols, rows);
+    }
+    if session.size == (cols, rows) {
+        return Ok(());
+    }
+    session.size = (cols, rows);
+
+    let mut recording = session
+        .recording
+        .lock()
+        .map_err(|_| "failed to lock recording state".to_string())?;
+    if let Some(active) = recording.as_mut() {
+        if let Err(err) = active.writer.write_resize(cols, rows) {
+            *recording = None;
+            return Err(format!("failed to write recording: {err}"));
+        }
+    }
+    Ok(())
+}
+
+fn foreground_pid(session: &PtySession) -> Option<u32> {
+    #[cfg(unix)]
+    let leader = session
+        .master
+        .process_group_leader()
+        .and_then(|pid| u32::try_from(pid).ok());
+    #[cfg(not(unix))]
+    let leader = None;
+    leader.or(session.info.pid)
+}
+
+fn foreground_process(session: &PtySession) -> Option<ForegroundProcess> {
+    if session.exit.wait(Duration::ZERO).is_some() {
+        return None;
+    }
+    let pgid = foreground_pid(session)?;
+    Some(ForegroundProcess::new(
+        session.info.session_id,
+        pgid,
+        session.info.pid,
+        &session.shutdown.idle_processes,
+    ))
+}
+
+fn busy_foreground(session: &PtySession) -> Option<ForegroundProcess> {
+    if !session.shutdown.confirm_close {
+        return None;
+    }
+    foreground_process(session).filter(|process| !process.idle)
+}
+
+// The foreground group comes from tcgetpgrp on the master, so a job started by the
+// shell is reached even when it has disabled ISIG or is not the direct child.
+#[cfg(unix)]
+fn signal_foreground_group(session: &PtySession, signal: &str) -> Result<u32, String> {
+    let signal = parse_signal(signal)?;
+    let pgid = session
+        .master
+        .process_group_leader()
+        .and_then(|pid| u32::try_from(pid).ok())
+        .ok_or_else(|| {
+            format!(
+                "foreground process group of session {} is unknown",
+                session.info.session_id
+            )
+        })?;
+    send_to_group(pgid, signal)?;
+    Ok(pgid)
+}
+
+#[cfg(not(unix))]
+fn signal_foreground_group(_session: &PtySession, _signal: &str) -> Result<u32, String> {
+    Err("signals are not supported on this platform".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn process_cwd(pid: u32) -> Option<String> {
+    fs::read_link(format!("/proc/{pid}/cwd"))
+        .ok()
+        .map(|path| path.display().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cwd(_pid: u32) -> Option<String> {
+    None
+}
+
+fn open_capture_filter(app: &AppHandle, config: &RedactionConfig) -> CaptureFilter {
+    let (filter, errors) = CaptureFilter::new(config);
+    if !errors.is_empty() {
+        emit_warning(
+            app,
+            format!(
+                "秘匿ルールの一部を読み込めませんでした: {}",
+                errors.join(", ")
+            ),
+        );
+    }
+    filter
+}
+
+fn open_transcript(
+    app: &AppHandle,
+    config: &LoggingConfig,
+    session_id: SessionId,
+    command: &str,
+) -> Option<TranscriptLog> {
+    if !config.enabled {
+        return None;
+    }
+    let file_name = render_file_name(&config.file_name, &Local::now(), session_id, command);
+    let path = resolve_log_dir(&resolve_state_dir(), &config.directory).join(file_name);
+    match TranscriptLog::create(path.clone(), config) {
+        Ok(log) => Some(log),
+        Err(err) => {
+            emit_warning(
+                app,
+                format!(
+                    "セッションログを開けませんでした ({}): {err}",
+                    path.display()
+                ),
+            );
+            None
+        }
+    }
+}
+
+fn shutdown_session(mut session: PtySession) -> ProcessEnd {
+    session.active.store(false, Ordering::Release);
+    session.output.close();
+    let end = stop_process(
+        session.info.pid,
+        foreground_pid(&session),
+        &session.exit,
+        &session.shutdown,
+        session.killer.as_mut(),
+    );
+    let recording = session
+        .recording
+        .lock()
+        .ok()
+        .and_then(|mut recording| recording.take());
+    if let Some(active) = recording {
+        let path = active.path.display().to_string();
+        if let Err(err) = active.finish() {
+            eprintln!(
+                "failed to finish recording {path} for session {}: {err}",
+                session.info.session_id
+            );
+        }
+    }
+    end
+}
+
 #[derive(Debug, Clone)]
 struct ResolvedCommand {
     program: String,
     args: Vec<String>,
     display: String,
+    integration: Option<Injection>,
     fallback_used: bool,
     warning: Option<String>,
 }
@@ -215,6 +1280,22 @@ fn resolve_startup_command_with_checker<F>(
 where
     F: Fn(&str) -> bool,
 {
+    if let Some(command) = startup
+        .command
+        .as_deref()
+        .filter(|command| !command.trim().is_empty())
+    {
+        let (program, args) = parse_command_line(command)?;
+        return Ok(ResolvedCommand {
+            display: join_command(&program, &args),
+            program,
+            args,
+            integration: None,
+            fallback_used: false,
+            warning: None,
+        });
+    }
+
     match startup.multiplexer {
         MultiplexerMode::None => Ok(resolve_shell_command(startup, false, None)),
         MultiplexerMode::Tmux => {
@@ -224,6 +1305,7 @@ where
                     display: join_command(&program, &args),
                     program,
                     args,
+                    integration: None,
                     fallback_used: false,
                     warning: None,
                 })
@@ -246,6 +1328,7 @@ where
                     display: join_command(&program, &args),
                     program,
                     args,
+                    integration: None,
                     fallback_used: false,
                     warning: None,
                 })
@@ -290,11 +1373,20 @@ fn resolve_shell_command(
     if args.is_empty() {
         args.push("-l".to_string());
     }
+    let display = join_command(&shell, &args);
+
+    // The scripts are only written when the session is spawned; resolving stays side-effect free.
+    let integration = ShellKind::detect(&shell)
+        .filter(|_| startup.shell_integration)
+        .and_then(|kind| {
+            shell_integration::inject(kind, &integration_dir(), &args, |name| env::var(name).ok())
+        });
 
     ResolvedCommand {
-        display: join_command(&shell, &args),
+        display,
         program: shell,
         args,
+        integration,
         fallback_used,
         warning,
     }
@@ -357,6 +1449,8 @@ mod tests {
             shell_args: vec!["-l".to_string()],
             zellij_command: "zellij attach -c d3term".to_string(),
             tmux_command: "tmux new-session -A -s main".to_string(),
+            shell_integration: false,
+            ..StartupConfig::default()
         };
         let resolved = resolve_startup_command_with_checker(&startup, |_program| false)
             .expect("fallback should resolve");
@@ -372,6 +1466,8 @@ mod tests {
             shell_args: vec![],
             zellij_command: "zellij attach -c d3term".to_string(),
             tmux_command: "tmux new-session -A -s main".to_string(),
+            shell_integration: false,
+            ..StartupConfig::default()
         };
         let resolved = resolve_startup_command_with_checker(&startup, |_program| true)
             .expect("shell mode should resolve");
@@ -387,10 +1483,27 @@ mod tests {
             shell_args: vec![],
             zellij_command: "zellij attach -c".to_string(),
             tmux_command: "tmux new-session -A -s main".to_string(),
+            shell_integration: false,
+            ..StartupConfig::default()
         };
         let resolved = resolve_startup_command_with_checker(&startup, |_program| true)
             .expect("zellij command should resolve");
         assert_eq!(resolved.program, "zellij");
         assert_eq!(resolved.args, vec!["attach", "-c", "d3term"]);
     }
+
+    #[test]
+    fn explicit_command_bypasses_multiplexer_and_shell() {
+        let startup = StartupConfig {
+            multiplexer: MultiplexerMode::Zellij,
+            command: Some("docker exec -it 'prod db' bash".to_string()),
+            ..StartupConfig::default()
+        };
+        let resolved = resolve_startup_command_with_checker(&startup, |_program| false)
+            .expect("command should resolve");
+        assert_eq!(resolved.program, "docker");
+        assert_eq!(resolved.args, vec!["exec", "-it", "prod db", "bash"]);
+        assert!(!resolved.fallback_used);
+        assert!(resolved.integration.is_none());
+    }
 }
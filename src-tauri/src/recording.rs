@@ -0,0 +1,199 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::utf8::Utf8StreamDecoder;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AsciicastHeader {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    pub env: AsciicastEnv,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AsciicastEnv {
+    #[serde(rename = "TERM")]
+    pub term: String,
+    #[serde(rename = "SHELL", skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+}
+
+impl AsciicastHeader {
+    pub fn new(width: u16, height: u16, command: Option<String>) -> Self {
+        Self {
+            version: 2,
+            width,
+            height,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            command,
+            env: AsciicastEnv {
+                term: "xterm-256color".to_string(),
+                shell: std::env::var("SHELL").ok(),
+            },
+        }
+    }
+}
+
+pub struct AsciicastWriter<W: Write> {
+    writer: W,
+    started_at: Instant,
+    decoder: Utf8StreamDecoder,
+}
+
+impl AsciicastWriter<BufWriter<File>> {
+    pub fn create(path: &Path, header: &AsciicastHeader) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Self::new(BufWriter::new(File::create(path)?), header)
+    }
+}
+
+impl<W: Write> AsciicastWriter<W> {
+    pub fn new(mut writer: W, header: &AsciicastHeader) -> io::Result<Self> {
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n")?;
+        Ok(Self {
+            writer,
+            started_at: Instant::now(),
+            decoder: Utf8StreamDecoder::new(),
+        })
+    }
+
+    pub fn write_output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let data = self.decoder.decode(bytes);
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.write_event("o", &data)
+    }
+
+    pub fn write_resize(&mut self, cols: u16, rows: u16) -> io::Result<()> {
+        self.write_event("r", &format!("{cols}x{rows}"))
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let data = self.decoder.finish();
+        if !data.is_empty() {
+            self.write_event("o", &data)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_event(&mut self, kind: &str, data: &str) -> io::Result<()> {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        serde_json::to_writer(&mut self.writer, &(elapsed, kind, data))?;
+        self.writer.write_all(b"\n")?;
+        // Each event is one PTY read, so a crash loses at most the read in flight.
+        self.writer.flush()
+    }
+}
+
+pub fn recording_file_name(session_id: u64, timestamp: u64) -> String {
+    format!("d3term-{timestamp}-session{session_id}.cast")
+}
+
+pub fn resolve_recording_dir(state_dir: &Path, directory: &str) -> PathBuf {
+    let directory = directory.trim();
+    if directory.is_empty() {
+        return state_dir.join("recordings");
+    }
+    state_dir.join(directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn lines(bytes: &[u8]) -> Vec<Value> {
+        String::from_utf8(bytes.to_vec())
+            .expect("cast file should be UTF-8")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line should be JSON"))
+            .collect()
+    }
+
+    #[test]
+    fn header_and_events_follow_asciicast_v2() {
+        let header = AsciicastHeader::new(80, 24, Some("zsh -l".to_string()));
+        let mut writer = AsciicastWriter::new(Vec::new(), &header).expect("header");
+        writer.write_output(b"$ ls\r\n").expect("output");
+        writer.write_resize(100, 30).expect("resize");
+        let bytes = writer.finish().expect("finish");
+
+        let lines = lines(&bytes);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[0]["height"], 24);
+        assert_eq!(lines[0]["command"], "zsh -l");
+        assert_eq!(lines[0]["env"]["TERM"], "xterm-256color");
+        assert!(lines[1][0].as_f64().expect("timestamp") >= 0.0);
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "$ ls\r\n");
+        assert_eq!(lines[2][1], "r");
+        assert_eq!(lines[2][2], "100x30");
+    }
+
+    #[test]
+    fn multibyte_output_split_across_reads_is_kept_intact() {
+        let header = AsciicastHeader::new(80, 24, None);
+        let mut writer = AsciicastWriter::new(Vec::new(), &header).expect("header");
+        let bytes = "日本語".as_bytes();
+        writer.write_output(&bytes[..4]).expect("first half");
+        writer.write_output(&bytes[4..]).expect("second half");
+        let bytes = writer.finish().expect("finish");
+
+        let lines = lines(&bytes);
+        assert!(lines[0].get("command").is_none());
+        let text = lines[1..]
+            .iter()
+            .map(|event| event[2].as_str().expect("data").to_string())
+            .collect::<String>();
+        assert_eq!(text, "日本語");
+    }
+
+    #[test]
+    fn events_reach_the_file_without_finish() {
+        let header = AsciicastHeader::new(80, 24, None);
+        let mut writer = AsciicastWriter::new(BufWriter::new(Vec::new()), &header).expect("header");
+        writer.write_output(b"$ ").expect("output");
+
+        let lines = lines(writer.writer.get_ref());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1][2], "$ ");
+    }
+
+    #[test]
+    fn recording_dir_is_resolved_under_state_dir() {
+        let state = Path::new("/tmp/state/d3term");
+        assert_eq!(
+            resolve_recording_dir(state, ""),
+            PathBuf::from("/tmp/state/d3term/recordings")
+        );
+        assert_eq!(
+            resolve_recording_dir(state, "casts"),
+            PathBuf::from("/tmp/state/d3term/casts")
+        );
+        assert_eq!(
+            resolve_recording_dir(state, "/var/casts"),
+            PathBuf::from("/var/casts")
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::{config::OnExit, pty::SessionId, state::AppState};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// A child that stayed up this long is considered healthy again and the backoff starts over.
+const STABLE_RUN: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitAction {
+    Hold,
+    Close,
+    Restart { delay: Duration, restarts: u32 },
+}
+
+pub fn exit_action(
+    on_exit: OnExit,
+    code: Option<i32>,
+    restarts: u32,
+    ran_for: Duration,
+) -> ExitAction {
+    let restart = match on_exit {
+        OnExit::Hold => false,
+        OnExit::Close => return ExitAction::Close,
+        OnExit::Restart => true,
+        OnExit::RestartOnFailure => code != Some(0),
+    };
+    if !restart {
+        return ExitAction::Hold;
+    }
+    let attempt = if ran_for >= STABLE_RUN { 0 } else { restarts };
+    let delay = INITIAL_BACKOFF
+        .saturating_mul(2_u32.saturating_pow(attempt.min(16)))
+        .min(MAX_BACKOFF);
+    ExitAction::Restart {
+        delay,
+        restarts: attempt + 1,
+    }
+}
+
+pub fn close_session_window(app: &AppHandle, session_id: SessionId) {
+    let state = app.state::<AppState>();
+    let Some(window) = state
+        .session
+        .window_label(session_id)
+        .and_then(|label| app.get_webview_window(&label))
+    else {
+        return;
+    };
+    if let Err(err) = window.destroy() {
+        eprintln!("failed to close window after session {session_id} exited: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUICK: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn policy_decides_between_hold_close_and_restart() {
+        assert_eq!(
+            exit_action(OnExit::Hold, Some(1), 0, QUICK),
+            ExitAction::Hold
+        );
+        assert_eq!(
+            exit_action(OnExit::Close, Some(0), 0, QUICK),
+            ExitAction::Close
+        );
+        assert_eq!(
+            exit_action(OnExit::RestartOnFailure, Some(0), 0, QUICK),
+            ExitAction::Hold
+        );
+        assert!(matches!(
+            exit_action(OnExit::RestartOnFailure, None, 0, QUICK),
+            ExitAction::Restart { .. }
+        ));
+        assert!(matches!(
+            exit_action(OnExit::Restart, Some(0), 0, QUICK),
+            ExitAction::Restart { .. }
+        ));
+    }
+
+    #[test]
+    fn quick_crashes_back_off_until_the_child_stays_up() {
+        let delays = (0..8)
+            .map(
+                |restarts| match exit_action(OnExit::Restart, Some(1), restarts, QUICK) {
+                    ExitAction::Restart { delay, .. } => delay.as_millis(),
+                    other => panic!("unexpected {other:?}"),
+                },
+            )
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            vec![500, 1000, 2000, 4000, 8000, 16000, 30000, 30000]
+        );
+        assert_eq!(
+            exit_action(OnExit::Restart, Some(1), 7, STABLE_RUN),
+            ExitAction::Restart {
+                delay: INITIAL_BACKOFF,
+                restarts: 1,
+            }
+        );
+    }
+}
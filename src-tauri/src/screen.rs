@@ -0,0 +1,932 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Parser, Perform};
+
+use crate::history::{CommandHistory, CommandRecord, Position};
+
+const BLANK: char = ' ';
+const WIDE_SPACER: char = '\0';
+const TAB_WIDTH: usize = 8;
+const EVENT_LIMIT: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalEvent {
+    CommandFinished {
+        command: String,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+    },
+    Notification {
+        title: Option<String>,
+        body: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenSnapshot {
+    pub cols: u16,
+    pub rows: u16,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+    pub cursor_visible: bool,
+    pub alternate_screen: bool,
+    pub title: String,
+    pub cwd: Option<String>,
+    pub lines: Vec<String>,
+    pub scrollback_len: usize,
+    pub scrollback: Option<Vec<String>>,
+}
+
+pub struct TerminalModel {
+    parser: Parser,
+    screen: Screen,
+}
+
+impl TerminalModel {
+    pub fn new(cols: u16, rows: u16, scrollback_limit: usize) -> Self {
+        Self {
+            parser: Parser::new(),
+            screen: Screen::new(cols.max(1) as usize, rows.max(1) as usize, scrollback_limit),
+        }
+    }
+
+    pub fn advance(&mut self, bytes: &[u8]) {
+        self.parser.advance(&mut self.screen, bytes);
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.screen
+            .resize(cols.max(1) as usize, rows.max(1) as usize);
+    }
+
+    pub fn title(&self) -> &str {
+        &self.screen.title
+    }
+
+    pub fn cwd(&self) -> Option<&str> {
+        self.screen.cwd.as_deref()
+    }
+
+    pub fn take_events(&mut self) -> Vec<TerminalEvent> {
+        std::mem::take(&mut self.screen.events)
+    }
+
+    pub fn commands(&self) -> Vec<CommandRecord> {
+        self.screen.history.records()
+    }
+
+    pub fn command_output(&self, index: usize) -> Option<String> {
+        let record = self.screen.history.get(index)?;
+        if let Some(output) = record.output() {
+            return Some(output.to_string());
+        }
+        let running = self.screen.history.running()?;
+        if running.index != index {
+            return None;
+        }
+        let start = self.screen.history.running_output_start()?;
+        Some(self.screen.text_between(start, self.screen.position()))
+    }
+
+    pub fn snapshot(&self, include_scrollback: bool) -> ScreenSnapshot {
+        let screen = &self.screen;
+        ScreenSnapshot {
+            cols: screen.cols as u16,
+            rows: screen.rows as u16,
+            cursor_row: screen.cursor.row as u16,
+            cursor_col: screen.cursor.col as u16,
+            cursor_visible: screen.cursor_visible,
+            alternate_screen: screen.saved_primary.is_some(),
+            title: screen.title.clone(),
+            cwd: screen.cwd.clone(),
+            lines: screen.lines.iter().map(|line| render_line(line)).collect(),
+            scrollback_len: screen.scrollback.len(),
+            scrollback: include_scrollback.then(|| screen.scrollback.iter().cloned().collect()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Cursor {
+    row: usize,
+    col: usize,
+}
+
+struct SavedScreen {
+    lines: Vec<Vec<char>>,
+    cursor: Cursor,
+}
+
+struct Screen {
+    cols: usize,
+    rows: usize,
+    lines: Vec<Vec<char>>,
+    scrollback: VecDeque<String>,
+    scrollback_limit: usize,
+    scrolled_off: usize,
+    cursor: Cursor,
+    pending_wrap: bool,
+    saved_cursor: Option<Cursor>,
+    saved_primary: Option<SavedScreen>,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    cursor_visible: bool,
+    title: String,
+    cwd: Option<String>,
+    history: CommandHistory,
+    events: Vec<TerminalEvent>,
+}
+
+impl Screen {
+    fn new(cols: usize, rows: usize, scrollback_limit: usize) -> Self {
+        Self {
+            cols,
+            rows,
+            lines: vec![vec![BLANK; cols]; rows],
+            scrollback: VecDeque::new(),
+            scrollback_limit,
+            scrolled_off: 0,
+            cursor: Cursor::default(),
+            pending_wrap: false,
+            saved_cursor: None,
+            saved_primary: None,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            cursor_visible: true,
+            title: String::new(),
+            cwd: None,
+            history: CommandHistory::new(),
+            events: Vec::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        let scrollback = std::mem::take(&mut self.scrollback);
+        let cwd = self.cwd.take();
+        let history = std::mem::replace(&mut self.history, CommandHistory::new());
+        let events = std::mem::take(&mut self.events);
+        let scrolled_off = self.scrolled_off;
+        *self = Self::new(self.cols, self.rows, self.scrollback_limit);
+        self.scrollback = scrollback;
+        self.scrolled_off = scrolled_off;
+        self.cwd = cwd;
+        self.history = history;
+        self.events = events;
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        for line in &mut self.lines {
+            line.resize(cols, BLANK);
+        }
+        if let Some(saved) = self.saved_primary.as_mut() {
+            for line in &mut saved.lines {
+                line.resize(cols, BLANK);
+            }
+            saved.lines.resize(rows, vec![BLANK; cols]);
+            saved.cursor.row = saved.cursor.row.min(rows - 1);
+            saved.cursor.col = saved.cursor.col.min(cols - 1);
+        }
+
+        if rows < self.rows {
+            let overflow = (self.cursor.row + 1).saturating_sub(rows);
+            for line in self.lines.drain(..overflow).collect::<Vec<_>>() {
+                self.push_scrollback(&line);
+            }
+            self.cursor.row -= overflow;
+        }
+        self.lines.resize(rows, vec![BLANK; cols]);
+
+        self.cols = cols;
+        self.rows = rows;
+        self.cursor.row = self.cursor.row.min(rows - 1);
+        self.cursor.col = self.cursor.col.min(cols - 1);
+        self.pending_wrap = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+    }
+
+    fn push_scrollback(&mut self, line: &[char]) {
+        if self.saved_primary.is_some() {
+            return;
+        }
+        self.scrolled_off += 1;
+        if self.scrollback_limit == 0 {
+            return;
+        }
+        if self.scrollback.len() == self.scrollback_limit {
+            self.scrollback.pop_front();
+        }
+        self.scrollback.push_back(render_line(line));
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.scrolled_off + self.cursor.row,
+            col: self.cursor.col,
+        }
+    }
+
+    fn primary_line(&self, line: usize) -> Option<Vec<char>> {
+        if let Some(row) = line.checked_sub(self.scrolled_off) {
+            let lines = match self.saved_primary.as_ref() {
+                Some(saved) => &saved.lines,
+                None => &self.lines,
+            };
+            return lines.get(row).cloned();
+        }
+        let first_kept = self.scrolled_off - self.scrollback.len();
+        line.checked_sub(first_kept)
+            .and_then(|index| self.scrollback.get(index))
+            .map(|text| text.chars().collect())
+    }
+
+    fn text_between(&self, start: Position, end: Position) -> String {
+        let mut lines = Vec::new();
+        for line in start.line..=end.line {
+            let Some(chars) = self.primary_line(line) else {
+                continue;
+            };
+            let from = if line == start.line { start.col } else { 0 };
+            let to = if line == end.line {
+                end.col
+            } else {
+                chars.len()
+            };
+            let to = to.min(chars.len());
+            lines.push(render_line(&chars[from.min(to)..to]));
+        }
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+
+    fn shell_mark(&mut self, mark: &[u8], params: &[&[u8]]) {
+        if self.saved_primary.is_some() {
+            return;
+        }
+        let position = self.position();
+        match mark {
+            b"A" => {
+                let unfinished = self
+                    .history
+                    .running_output_start()
+                    .map(|start| self.text_between(start, position));
+                self.history.prompt_start(position.line, unfinished);
+            }
+            b"B" => self.history.command_start(position),
+            b"C" => {
+                let command = self
+                    .history
+                    .command_start_position()
+                    .map(|start| self.text_between(start, position))
+                    .unwrap_or_default();
+                self.history
+                    .output_start(position, command, self.cwd.clone());
+            }
+            b"D" => {
+                let exit_code = params
+                    .first()
+                    .and_then(|code| std::str::from_utf8(code).ok())
+                    .and_then(|code| code.parse().ok());
+                let output = self
+                    .history
+                    .running_output_start()
+                    .map(|start| self.text_between(start, position))
+                    .unwrap_or_default();
+                let event = self.history.finish(output, exit_code).and_then(|record| {
+                    Some(TerminalEvent::CommandFinished {
+                        command: record.command.clone(),
+                        exit_code: record.exit_code,
+                        duration_ms: record.finished_at?.saturating_sub(record.started_at?),
+                    })
+                });
+                if let Some(event) = event {
+                    self.push_event(event);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn notify(&mut self, params: &[&[u8]]) {
+        let text = |param: &[u8]| String::from_utf8_lossy(param).into_owned();
+        let event = match params {
+            // OSC 9;<digits> are ConEmu progress/control codes, not iTerm2 notifications.
+            [b"9", body @ ..] if !body.is_empty() && !body[0].iter().all(u8::is_ascii_digit) => {
+                TerminalEvent::Notification {
+                    title: None,
+                    body: text(&body.join(&b';')),
+                }
+            }
+            [b"777", b"notify", title, body @ ..] => TerminalEvent::Notification {
+                title: Some(text(title)).filter(|title| !title.is_empty()),
+                body: text(&body.join(&b';')),
+            },
+            _ => return,
+        };
+        self.push_event(event);
+    }
+
+    fn push_event(&mut self, event: TerminalEvent) {
+        // Playback sessions never drain events, so keep only the most recent ones.
+        if self.events.len() == EVENT_LIMIT {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    fn blank_line(&self) -> Vec<char> {
+        vec![BLANK; self.cols]
+    }
+
+    fn scroll_up(&mut self, count: usize) {
+        for _ in 0..count.min(self.scroll_bottom - self.scroll_top + 1) {
+            let line = self.lines.remove(self.scroll_top);
+            if self.scroll_top == 0 {
+                self.push_scrollback(&line);
+            }
+            self.lines.insert(self.scroll_bottom, self.blank_line());
+        }
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        for _ in 0..count.min(self.scroll_bottom - self.scroll_top + 1) {
+            self.lines.remove(self.scroll_bottom);
+            self.lines.insert(self.scroll_top, self.blank_line());
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.pending_wrap = false;
+        if self.cursor.row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor.row + 1 < self.rows {
+            self.cursor.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.pending_wrap = false;
+        if self.cursor.row == self.scroll_top {
+            self.scroll_down(1);
+        } else if self.cursor.row > 0 {
+            self.cursor.row -= 1;
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        self.pending_wrap = false;
+        self.cursor.col = 0;
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.pending_wrap = false;
+        self.cursor.row = row.min(self.rows - 1);
+        self.cursor.col = col.min(self.cols - 1);
+    }
+
+    fn write_char(&mut self, c: char, width: usize) {
+        if self.pending_wrap {
+            self.carriage_return();
+            self.linefeed();
+        }
+        if self.cursor.col + width > self.cols {
+            if width > self.cols {
+                return;
+            }
+            self.carriage_return();
+            self.linefeed();
+        }
+
+        let Cursor { row, col } = self.cursor;
+        let line = &mut self.lines[row];
+        if line[col] == WIDE_SPACER && col > 0 {
+            line[col - 1] = BLANK;
+        }
+        line[col] = c;
+        if width == 2 {
+            line[col + 1] = WIDE_SPACER;
+        }
+        if let Some(next) = line.get_mut(col + width) {
+            if *next == WIDE_SPACER {
+                *next = BLANK;
+            }
+        }
+
+        if col + width >= self.cols {
+            self.cursor.col = self.cols - 1;
+            self.pending_wrap = true;
+        } else {
+            self.cursor.col = col + width;
+        }
+    }
+
+    fn erase_in_display(&mut self, mode: usize) {
+        let Cursor { row, col } = self.cursor;
+        match mode {
+            0 => {
+                self.lines[row][col..].fill(BLANK);
+                for line in &mut self.lines[row + 1..] {
+                    line.fill(BLANK);
+                }
+            }
+            1 => {
+                for line in &mut self.lines[..row] {
+                    line.fill(BLANK);
+                }
+                self.lines[row][..=col].fill(BLANK);
+            }
+            2 => {
+                for line in &mut self.lines {
+                    line.fill(BLANK);
+                }
+            }
+            3 => self.scrollback.clear(),
+            _ => {}
+        }
+    }
+
+    fn erase_in_line(&mut self, mode: usize) {
+        let Cursor { row, col } = self.cursor;
+        let line = &mut self.lines[row];
+        match mode {
+            0 => line[col..].fill(BLANK),
+            1 => line[..=col].fill(BLANK),
+            2 => line.fill(BLANK),
+            _ => {}
+        }
+    }
+
+    fn insert_lines(&mut self, count: usize) {
+        let row = self.cursor.row;
+        if row < self.scroll_top || row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..count.min(self.scroll_bottom - row + 1) {
+            self.lines.remove(self.scroll_bottom);
+            self.lines.insert(row, self.blank_line());
+        }
+        self.carriage_return();
+    }
+
+    fn delete_lines(&mut self, count: usize) {
+        let row = self.cursor.row;
+        if row < self.scroll_top || row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..count.min(self.scroll_bottom - row + 1) {
+            self.lines.remove(row);
+            self.lines.insert(self.scroll_bottom, self.blank_line());
+        }
+        self.carriage_return();
+    }
+
+    fn insert_chars(&mut self, count: usize) {
+        let Cursor { row, col } = self.cursor;
+        let cols = self.cols;
+        let line = &mut self.lines[row];
+        let count = count.min(cols - col);
+        line.splice(col..col, std::iter::repeat_n(BLANK, count));
+        line.truncate(cols);
+    }
+
+    fn delete_chars(&mut self, count: usize) {
+        let Cursor { row, col } = self.cursor;
+        let line = &mut self.lines[row];
+        let count = count.min(line.len() - col);
+        line.drain(col..col + count);
+        line.extend(std::iter::repeat_n(BLANK, count));
+    }
+
+    fn erase_chars(&mut self, count: usize) {
+        let Cursor { row, col } = self.cursor;
+        let end = (col + count).min(self.cols);
+        self.lines[row][col..end].fill(BLANK);
+    }
+
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let top = top.saturating_sub(1);
+        let bottom = if bottom == 0 {
+            self.rows
+        } else {
+            bottom.min(self.rows)
+        } - 1;
+        if top < bottom {
+            self.scroll_top = top;
+            self.scroll_bottom = bottom;
+            self.move_to(0, 0);
+        }
+    }
+
+    fn enter_alternate_screen(&mut self, save_cursor: bool) {
+        if self.saved_primary.is_some() {
+            return;
+        }
+        if save_cursor {
+            self.saved_cursor = Some(self.cursor);
+        }
+        let blank = vec![self.blank_line(); self.rows];
+        let lines = std::mem::replace(&mut self.lines, blank);
+        self.saved_primary = Some(SavedScreen {
+            lines,
+            cursor: self.cursor,
+        });
+    }
+
+    fn leave_alternate_screen(&mut self, restore_cursor: bool) {
+        let Some(saved) = self.saved_primary.take() else {
+            return;
+        };
+        self.lines = saved.lines;
+        self.cursor = saved.cursor;
+        if restore_cursor {
+            if let Some(cursor) = self.saved_cursor {
+                self.cursor = cursor;
+            }
+        }
+        self.pending_wrap = false;
+    }
+
+    fn set_private_mode(&mut self, mode: u16, enabled: bool) {
+        match mode {
+            25 => self.cursor_visible = enabled,
+            47 | 1047 if enabled => self.enter_alternate_screen(false),
+            47 | 1047 => self.leave_alternate_screen(false),
+            1049 if enabled => self.enter_alternate_screen(true),
+            1049 => self.leave_alternate_screen(true),
+            _ => {}
+        }
+    }
+}
+
+impl Perform for Screen {
+    fn print(&mut self, c: char) {
+        if let Some(width @ 1..=2) = c.width() {
+            self.write_char(c, width);
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            0x08 => {
+                self.pending_wrap = false;
+                self.cursor.col = self.cursor.col.saturating_sub(1);
+            }
+            0x09 => {
+                let next = (self.cursor.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor.col = next.min(self.cols - 1);
+            }
+            0x0A..=0x0C => self.linefeed(),
+            0x0D => self.carriage_return(),
+            _ => {}
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            [b"0" | b"2", title @ ..] => {
+                self.title = String::from_utf8_lossy(&title.join(&b';')).into_owned();
+            }
+            [b"7", uri @ ..] => {
+                if let Some(path) = parse_file_uri(&uri.join(&b';')) {
+                    self.cwd = Some(path);
+                }
+            }
+            [b"133", mark, rest @ ..] => self.shell_mark(mark, rest),
+            [b"9" | b"777", ..] => self.notify(params),
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore {
+            return;
+        }
+        if intermediates == b"?" {
+            if action == 'h' || action == 'l' {
+                for mode in params.iter().filter_map(|param| param.first().copied()) {
+                    self.set_private_mode(mode, action == 'h');
+                }
+            }
+            return;
+        }
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        let Cursor { row, col } = self.cursor;
+        let count = param_or(params, 0, 1);
+        match action {
+            '@' => self.insert_chars(count),
+            'A' => self.move_to(row.saturating_sub(count), col),
+            'B' | 'e' => self.move_to(row + count, col),
+            'C' | 'a' => self.move_to(row, col + count),
+            'D' => self.move_to(row, col.saturating_sub(count)),
+            'E' => self.move_to(row + count, 0),
+            'F' => self.move_to(row.saturating_sub(count), 0),
+            'G' | '`' => self.move_to(row, count - 1),
+            'H' | 'f' => self.move_to(count - 1, param_or(params, 1, 1) - 1),
+            'd' => self.move_to(count - 1, col),
+            'J' => self.erase_in_display(param_or(params, 0, 0)),
+            'K' => self.erase_in_line(param_or(params, 0, 0)),
+            'L' => self.insert_lines(count),
+            'M' => self.delete_lines(count),
+            'P' => self.delete_chars(count),
+            'X' => self.erase_chars(count),
+            'S' => self.scroll_up(count),
+            'T' => self.scroll_down(count),
+            'r' => self.set_scroll_region(param_or(params, 0, 1), param_or(params, 1, 0)),
+            's' => self.saved_cursor = Some(self.cursor),
+            'u' => {
+                if let Some(cursor) = self.saved_cursor {
+                    self.move_to(cursor.row, cursor.col);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], ignore: bool, byte: u8) {
+        if ignore || !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.saved_cursor = Some(self.cursor),
+            b'8' => {
+                if let Some(cursor) = self.saved_cursor {
+                    self.move_to(cursor.row, cursor.col);
+                }
+            }
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.carriage_return();
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => self.reset(),
+            _ => {}
+        }
+    }
+}
+
+fn param_or(params: &Params, index: usize, default: usize) -> usize {
+    match params.iter().nth(index).and_then(|param| param.first()) {
+        Some(0) | None => default,
+        Some(value) => *value as usize,
+    }
+}
+
+fn parse_file_uri(uri: &[u8]) -> Option<String> {
+    let rest = uri
+        .strip_prefix(b"file://")
+        .or_else(|| uri.strip_prefix(b"kitty-shell-cwd://"))?;
+    let path = &rest[rest.iter().position(|byte| *byte == b'/')?..];
+
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut index = 0;
+    while index < path.len() {
+        let escaped = path
+            .get(index + 1..index + 3)
+            .filter(|_| path[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(path[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn render_line(line: &[char]) -> String {
+    let text = line
+        .iter()
+        .filter(|c| **c != WIDE_SPACER)
+        .collect::<String>();
+    text.trim_end_matches(BLANK).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(cols: u16, rows: u16) -> TerminalModel {
+        TerminalModel::new(cols, rows, 100)
+    }
+
+    #[test]
+    fn prints_text_and_tracks_cursor() {
+        let mut term = model(20, 4);
+        term.advance(b"$ ls\r\nfoo  bar");
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.lines[0], "$ ls");
+        assert_eq!(snapshot.lines[1], "foo  bar");
+        assert_eq!((snapshot.cursor_row, snapshot.cursor_col), (1, 8));
+    }
+
+    #[test]
+    fn lines_scrolled_off_the_top_go_to_scrollback() {
+        let mut term = TerminalModel::new(10, 2, 2);
+        term.advance(b"one\r\ntwo\r\nthree\r\nfour");
+        let snapshot = term.snapshot(true);
+        assert_eq!(snapshot.lines, vec!["three", "four"]);
+        assert_eq!(snapshot.scrollback, Some(vec!["one".into(), "two".into()]));
+
+        term.advance(b"\r\nfive");
+        let snapshot = term.snapshot(true);
+        assert_eq!(snapshot.scrollback_len, 2);
+        assert_eq!(
+            snapshot.scrollback,
+            Some(vec!["two".into(), "three".into()])
+        );
+    }
+
+    #[test]
+    fn long_lines_wrap_at_the_right_margin() {
+        let mut term = model(4, 3);
+        term.advance(b"abcdef");
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.lines[0], "abcd");
+        assert_eq!(snapshot.lines[1], "ef");
+        assert_eq!((snapshot.cursor_row, snapshot.cursor_col), (1, 2));
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let mut term = model(10, 2);
+        term.advance("日本語".as_bytes());
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.lines[0], "日本語");
+        assert_eq!(snapshot.cursor_col, 6);
+    }
+
+    #[test]
+    fn cursor_movement_and_erase_sequences() {
+        let mut term = model(10, 3);
+        term.advance(b"hello\r\nworld");
+        term.advance(b"\x1b[1;3H\x1b[K");
+        term.advance(b"\x1b[2;2H\x1b[1P");
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.lines[0], "he");
+        assert_eq!(snapshot.lines[1], "wrld");
+        assert_eq!((snapshot.cursor_row, snapshot.cursor_col), (1, 1));
+
+        term.advance(b"\x1b[2J");
+        assert!(term
+            .snapshot(false)
+            .lines
+            .iter()
+            .all(|line| line.is_empty()));
+    }
+
+    #[test]
+    fn sequences_split_across_reads_are_applied() {
+        let mut term = model(10, 3);
+        term.advance(b"abc\x1b[");
+        term.advance(b"2;");
+        term.advance(b"5Hx\xe6\x97");
+        term.advance(b"\xa5");
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.lines[1], "    x日");
+    }
+
+    #[test]
+    fn alternate_screen_preserves_primary_contents() {
+        let mut term = model(10, 3);
+        term.advance(b"prompt$ ");
+        term.advance(b"\x1b[?1049h\x1b[Hvim buffer");
+        let snapshot = term.snapshot(false);
+        assert!(snapshot.alternate_screen);
+        assert_eq!(snapshot.lines[0], "vim buffer");
+
+        term.advance(b"\x1b[?1049l");
+        let snapshot = term.snapshot(false);
+        assert!(!snapshot.alternate_screen);
+        assert_eq!(snapshot.lines[0], "prompt$");
+        assert_eq!((snapshot.cursor_row, snapshot.cursor_col), (0, 8));
+    }
+
+    #[test]
+    fn osc_title_and_cursor_visibility_are_tracked() {
+        let mut term = model(10, 3);
+        term.advance(b"\x1b]2;build; make\x07\x1b[?25l");
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.title, "build; make");
+        assert!(!snapshot.cursor_visible);
+
+        term.advance(b"\x1b]0;zsh\x1b\\");
+        assert_eq!(term.snapshot(false).title, "zsh");
+    }
+
+    #[test]
+    fn osc7_updates_working_directory() {
+        let mut term = model(10, 3);
+        assert_eq!(term.cwd(), None);
+        term.advance(b"\x1b]7;file://host/home/user/my%20project\x07");
+        assert_eq!(term.cwd(), Some("/home/user/my project"));
+
+        term.advance("\x1b]7;file:///tmp/%E6%97%A5%E6%9C%AC\x1b\\\x1bc".as_bytes());
+        assert_eq!(term.cwd(), Some("/tmp/日本"));
+
+        term.advance(b"\x1b]7;not-a-uri\x07");
+        assert_eq!(term.snapshot(false).cwd.as_deref(), Some("/tmp/日本"));
+    }
+
+    #[test]
+    fn osc133_marks_build_command_history() {
+        let mut term = model(20, 3);
+        term.advance(b"\x1b]7;file:///srv\x07");
+        term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07echo hi\r\n\x1b]133;C\x07");
+        term.advance(b"hi\r\n");
+        assert_eq!(term.command_output(0).as_deref(), Some("hi"));
+        term.advance(b"\x1b]133;D;0\x07\x1b]133;A\x07$ \x1b]133;B\x07\r\n");
+        term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07false\r\n\x1b]133;C\x07");
+        term.advance(b"line 1\r\nline 2\r\n\x1b]133;D;1\x07\x1b]133;A\x07$ ");
+
+        let commands = term.commands();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].command, "echo hi");
+        assert_eq!(commands[0].cwd.as_deref(), Some("/srv"));
+        assert_eq!(commands[0].exit_code, Some(0));
+        assert_eq!(commands[1].command, "false");
+        assert_eq!(commands[1].exit_code, Some(1));
+        assert_eq!(commands[1].prompt_line, 3);
+        assert_eq!(
+            term.command_output(commands[1].index).as_deref(),
+            Some("line 1\nline 2")
+        );
+        assert_eq!(term.command_output(1), None);
+    }
+
+    #[test]
+    fn finished_commands_and_osc_notifications_are_reported() {
+        let mut term = model(20, 3);
+        term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07make\r\n\x1b]133;C\x07");
+        assert!(term.take_events().is_empty());
+        term.advance(b"\x1b]133;D;2\x07\x1b]9;4;1;50\x07\x1b]9;build done\x07");
+        term.advance(b"\x1b]777;notify;CI;passed; 3 jobs\x1b\\");
+
+        let events = term.take_events();
+        assert!(matches!(
+            &events[0],
+            TerminalEvent::CommandFinished { command, exit_code: Some(2), .. } if command == "make"
+        ));
+        assert_eq!(
+            events[1..],
+            [
+                TerminalEvent::Notification {
+                    title: None,
+                    body: "build done".to_string(),
+                },
+                TerminalEvent::Notification {
+                    title: Some("CI".to_string()),
+                    body: "passed; 3 jobs".to_string(),
+                },
+            ]
+        );
+        assert!(term.take_events().is_empty());
+    }
+
+    #[test]
+    fn interrupted_commands_keep_their_own_output() {
+        let mut term = model(20, 6);
+        term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07sleep 9\r\n\x1b]133;C\x07^C\r\n");
+        term.advance(b"\x1b]133;A\x07$ \x1b]133;B\x07echo other\r\n\x1b]133;C\x07other-output");
+
+        assert_eq!(term.command_output(0).as_deref(), Some("^C"));
+        assert_eq!(term.command_output(1).as_deref(), Some("other-output"));
+        let commands = term.commands();
+        assert_eq!(commands[0].exit_code, None);
+        assert!(commands[0].finished_at.is_some());
+        assert!(commands[1].finished_at.is_none());
+    }
+
+    #[test]
+    fn scroll_region_limits_scrolling() {
+        let mut term = model(10, 4);
+        term.advance(b"head\x1b[2;3r\x1b[2;1Ha\r\nb\r\nc");
+        let snapshot = term.snapshot(false);
+        assert_eq!(snapshot.lines, vec!["head", "b", "c", ""]);
+        assert_eq!(snapshot.scrollback_len, 0);
+    }
+
+    #[test]
+    fn resize_keeps_cursor_line_visible() {
+        let mut term = model(10, 4);
+        term.advance(b"1\r\n2\r\n3\r\n4");
+        term.resize(5, 2);
+        let snapshot = term.snapshot(true);
+        assert_eq!(snapshot.lines, vec!["3", "4"]);
+        assert_eq!(snapshot.scrollback, Some(vec!["1".into(), "2".into()]));
+        assert_eq!((snapshot.cursor_row, snapshot.cursor_col), (1, 1));
+    }
+}
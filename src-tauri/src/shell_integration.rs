@@ -0,0 +1,221 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::config::resolve_state_dir;
+
+const FILES: &[(&str, &str)] = &[
+    (
+        "zsh/.zshenv",
+        include_str!("../shell-integration/zsh/zshenv.zsh"),
+    ),
+    (
+        "zsh/.zprofile",
+        include_str!("../shell-integration/zsh/zprofile.zsh"),
+    ),
+    (
+        "zsh/.zshrc",
+        include_str!("../shell-integration/zsh/zshrc.zsh"),
+    ),
+    (
+        "zsh/.zlogin",
+        include_str!("../shell-integration/zsh/zlogin.zsh"),
+    ),
+    (
+        "zsh/d3term.zsh",
+        include_str!("../shell-integration/zsh/d3term.zsh"),
+    ),
+    (
+        "bash/d3term.bash",
+        include_str!("../shell-integration/bash/d3term.bash"),
+    ),
+    (
+        "fish-data/fish/vendor_conf.d/d3term.fish",
+        include_str!("../shell-integration/fish/d3term.fish"),
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Zsh,
+    Bash,
+    Fish,
+}
+
+impl ShellKind {
+    pub fn detect(program: &str) -> Option<Self> {
+        let name = Path::new(program).file_name()?.to_str()?;
+        match name.trim_start_matches('-') {
+            "zsh" => Some(Self::Zsh),
+            "bash" => Some(Self::Bash),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+pub fn integration_dir() -> PathBuf {
+    resolve_state_dir().join("shell-integration")
+}
+
+pub fn install(dir: &Path) -> io::Result<()> {
+    for (relative, contents) in FILES {
+        let path = dir.join(relative);
+        if fs::read_to_string(&path).is_ok_and(|current| current == *contents) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)?;
+    }
+    Ok(())
+}
+
+pub fn inject<F>(kind: ShellKind, dir: &Path, args: &[String], lookup: F) -> Option<Injection>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    // Scripts (`-c`, a file argument) do not read rc files, so there is nothing to hook into.
+    if args
+        .iter()
+        .any(|arg| arg == "-c" || arg == "--norc" || arg == "--noprofile" || !arg.starts_with('-'))
+    {
+        return None;
+    }
+
+    let mut env = vec![("D3TERM_SHELL_INTEGRATION".to_string(), "1".to_string())];
+    let args = match kind {
+        ShellKind::Zsh => {
+            let user_zdotdir = lookup("ZDOTDIR").or_else(|| lookup("HOME"))?;
+            env.push(("D3TERM_USER_ZDOTDIR".to_string(), user_zdotdir));
+            env.push(("ZDOTDIR".to_string(), path_string(&dir.join("zsh"))));
+            args.to_vec()
+        }
+        ShellKind::Bash => {
+            let login = args.iter().any(|arg| arg == "-l" || arg == "--login");
+            if login {
+                env.push(("D3TERM_BASH_LOGIN".to_string(), "1".to_string()));
+            }
+            let mut injected = vec![
+                "--rcfile".to_string(),
+                path_string(&dir.join("bash").join("d3term.bash")),
+            ];
+            injected.extend(
+                args.iter()
+                    .filter(|arg| *arg != "-l" && *arg != "--login")
+                    .cloned(),
+            );
+            injected
+        }
+        ShellKind::Fish => {
+            let original = lookup("XDG_DATA_DIRS").unwrap_or_default();
+            let base = if original.is_empty() {
+                "/usr/local/share:/usr/share"
+            } else {
+                &original
+            };
+            let data_dirs = format!("{}:{base}", path_string(&dir.join("fish-data")));
+            env.push(("D3TERM_FISH_XDG_DATA_DIRS".to_string(), original.clone()));
+            env.push(("XDG_DATA_DIRS".to_string(), data_dirs));
+            args.to_vec()
+        }
+    };
+    Some(Injection { args, env })
+}
+
+fn path_string(path: &Path) -> String {
+    path.display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/user".to_string()),
+            "XDG_DATA_DIRS" => Some("/opt/share".to_string()),
+            _ => None,
+        }
+    }
+
+    fn env_value<'a>(injection: &'a Injection, name: &str) -> Option<&'a str> {
+        injection
+            .env
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn detects_known_shells_by_file_name() {
+        assert_eq!(ShellKind::detect("/bin/zsh"), Some(ShellKind::Zsh));
+        assert_eq!(ShellKind::detect("-bash"), Some(ShellKind::Bash));
+        assert_eq!(
+            ShellKind::detect("/opt/homebrew/bin/fish"),
+            Some(ShellKind::Fish)
+        );
+        assert_eq!(ShellKind::detect("/bin/sh"), None);
+    }
+
+    #[test]
+    fn zsh_uses_a_zdotdir_shim_that_remembers_the_real_one() {
+        let dir = Path::new("/state/shell-integration");
+        let injection = inject(ShellKind::Zsh, dir, &["-l".to_string()], lookup)
+            .expect("zsh should be injected");
+        assert_eq!(injection.args, vec!["-l"]);
+        assert_eq!(
+            env_value(&injection, "ZDOTDIR"),
+            Some("/state/shell-integration/zsh")
+        );
+        assert_eq!(
+            env_value(&injection, "D3TERM_USER_ZDOTDIR"),
+            Some("/home/user")
+        );
+    }
+
+    #[test]
+    fn bash_login_shell_is_replaced_by_rcfile() {
+        let dir = Path::new("/state/shell-integration");
+        let injection = inject(ShellKind::Bash, dir, &["-l".to_string()], lookup)
+            .expect("bash should be injected");
+        assert_eq!(
+            injection.args,
+            vec!["--rcfile", "/state/shell-integration/bash/d3term.bash"]
+        );
+        assert_eq!(env_value(&injection, "D3TERM_BASH_LOGIN"), Some("1"));
+    }
+
+    #[test]
+    fn fish_prepends_vendor_data_dir() {
+        let dir = Path::new("/state/shell-integration");
+        let injection = inject(ShellKind::Fish, dir, &[], lookup).expect("fish should be injected");
+        assert_eq!(
+            env_value(&injection, "XDG_DATA_DIRS"),
+            Some("/state/shell-integration/fish-data:/opt/share")
+        );
+        assert_eq!(
+            env_value(&injection, "D3TERM_FISH_XDG_DATA_DIRS"),
+            Some("/opt/share")
+        );
+    }
+
+    #[test]
+    fn scripts_and_commands_are_left_alone() {
+        let dir = Path::new("/state/shell-integration");
+        let args = vec!["-c".to_string(), "make".to_string()];
+        assert_eq!(inject(ShellKind::Bash, dir, &args, lookup), None);
+        assert_eq!(
+            inject(ShellKind::Zsh, dir, &["script.zsh".to_string()], lookup),
+            None
+        );
+    }
+}
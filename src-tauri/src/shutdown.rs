@@ -0,0 +1,308 @@
+use std::{
+    io,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use portable_pty::{ChildKiller, ExitStatus};
+use serde::Serialize;
+
+use crate::config::ShutdownConfig;
+#[cfg(unix)]
+use crate::signal::signal_name;
+
+const KILL_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopMethod {
+    AlreadyExited,
+    Hangup,
+    Terminate,
+    Kill,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProcessEnd {
+    pub method: StopMethod,
+    pub exited: bool,
+    pub code: Option<i32>,
+    pub signal: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChildExit {
+    pub code: Option<i32>,
+    pub signal: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ChildExit {
+    pub fn from_wait(result: io::Result<ExitStatus>) -> Self {
+        let status = match result {
+            Ok(status) => status,
+            Err(err) => {
+                return Self {
+                    error: Some(err.to_string()),
+                    ..Self::default()
+                }
+            }
+        };
+        // A signalled child reports exit code 1, and portable-pty 0.8 has no accessor for the
+        // signal; it is only visible in Display, which child_exit_through_a_real_pty pins.
+        let text = status.to_string();
+        match text.strip_prefix("Terminated by ") {
+            Some(description) => Self {
+                signal: Some(describe_signal(description)),
+                ..Self::default()
+            },
+            None => Self {
+                code: i32::try_from(status.exit_code()).ok(),
+                ..Self::default()
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+fn describe_signal(description: &str) -> String {
+    signal_name(description).unwrap_or_else(|| description.to_string())
+}
+
+#[cfg(not(unix))]
+fn describe_signal(description: &str) -> String {
+    description.to_string()
+}
+
+#[derive(Debug, Default)]
+pub struct ExitWatch {
+    status: Mutex<Option<ChildExit>>,
+    changed: Condvar,
+}
+
+impl ExitWatch {
+    pub fn set(&self, exit: ChildExit) {
+        if let Ok(mut status) = self.status.lock() {
+            *status = Some(exit);
+        }
+        self.changed.notify_all();
+    }
+
+    pub fn wait(&self, timeout: Duration) -> Option<ChildExit> {
+        let deadline = Instant::now() + timeout;
+        let mut status = self.status.lock().ok()?;
+        loop {
+            if let Some(exit) = status.as_ref() {
+                return Some(exit.clone());
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            status = self
+                .changed
+                .wait_timeout(status, remaining)
+                .ok()
+                .map(|(guard, _)| guard)?;
+        }
+    }
+}
+
+pub fn stop_process(
+    pid: Option<u32>,
+    foreground: Option<u32>,
+    exit: &ExitWatch,
+    config: &ShutdownConfig,
+    killer: &mut dyn ChildKiller,
+) -> ProcessEnd {
+    if let Some(end) = wait_for(exit, StopMethod::AlreadyExited, Duration::ZERO) {
+        return end;
+    }
+
+    #[cfg(unix)]
+    if let Some(pid) = pid.and_then(|pid| i32::try_from(pid).ok()) {
+        // The child is a session leader, so its pid is also its process group id. A shell with
+        // job control puts each job in a group of its own, which kill(-pid) never reaches, so
+        // the foreground job's group is signalled alongside it.
+        let foreground = foreground
+            .and_then(|pgid| i32::try_from(pgid).ok())
+            .filter(|pgid| *pgid != pid);
+        let groups = [Some(pid), foreground];
+        let signal_groups = |signal| {
+            for pgid in groups.iter().flatten() {
+                unsafe { libc::kill(-pgid, signal) };
+            }
+        };
+        let steps = [
+            (StopMethod::Hangup, libc::SIGHUP, config.hangup_timeout_ms),
+            (
+                StopMethod::Terminate,
+                libc::SIGTERM,
+                config.terminate_timeout_ms,
+            ),
+        ];
+        for (method, signal, timeout_ms) in steps {
+            signal_groups(signal);
+            if let Some(end) = wait_for(exit, method, Duration::from_millis(timeout_ms)) {
+                return end;
+            }
+        }
+        signal_groups(libc::SIGKILL);
+    } else {
+        let _ = killer.kill();
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (pid, foreground, config);
+        let _ = killer.kill();
+    }
+
+    match wait_for(exit, StopMethod::Kill, KILL_TIMEOUT) {
+        Some(end) => end,
+        None => ProcessEnd {
+            method: StopMethod::Kill,
+            exited: false,
+            code: None,
+            signal: None,
+        },
+    }
+}
+
+fn wait_for(exit: &ExitWatch, method: StopMethod, timeout: Duration) -> Option<ProcessEnd> {
+    exit.wait(timeout).map(|exit| ProcessEnd {
+        method,
+        exited: true,
+        code: exit.code,
+        signal: exit.signal,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        os::unix::process::CommandExt,
+        process::{Command, Stdio},
+        sync::Arc,
+        thread,
+    };
+
+    use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+
+    use super::*;
+
+    #[test]
+    fn child_exit_separates_codes_signals_and_errors() {
+        assert_eq!(
+            ChildExit::from_wait(Ok(ExitStatus::with_exit_code(3))).code,
+            Some(3)
+        );
+        let killed = Command::new("/bin/sh")
+            .args(["-c", "kill -KILL $$"])
+            .status()
+            .expect("run sh");
+        assert_eq!(
+            ChildExit::from_wait(Ok(killed.into())),
+            ChildExit {
+                signal: Some("SIGKILL".to_string()),
+                ..ChildExit::default()
+            }
+        );
+        let failed = ChildExit::from_wait(Err(io::Error::other("wait failed")));
+        assert_eq!(failed.code, None);
+        assert_eq!(failed.error.as_deref(), Some("wait failed"));
+    }
+
+    #[test]
+    fn child_exit_through_a_real_pty() {
+        let pair = native_pty_system()
+            .openpty(PtySize::default())
+            .expect("open pty");
+        let mut command = CommandBuilder::new("/bin/sh");
+        command.args(["-c", "kill -TERM $$"]);
+        let mut child = pair.slave.spawn_command(command).expect("spawn sh");
+        assert_eq!(
+            ChildExit::from_wait(child.wait()).signal.as_deref(),
+            Some("SIGTERM")
+        );
+    }
+
+    fn spawn_group(script: &str) -> (u32, Arc<ExitWatch>) {
+        let mut child = Command::new("/bin/sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .expect("spawn sh");
+        let pid = child.id();
+        let exit = Arc::new(ExitWatch::default());
+        let exit_for_waiter = Arc::clone(&exit);
+        let mut stdout = child.stdout.take().expect("stdout");
+        // Wait for the traps to be installed before signalling.
+        let _ = std::io::Read::read(&mut stdout, &mut [0_u8; 1]);
+        thread::spawn(move || {
+            exit_for_waiter.set(ChildExit::from_wait(child.wait().map(Into::into)));
+        });
+        (pid, exit)
+    }
+
+    #[derive(Debug)]
+    struct NoopKiller;
+
+    impl ChildKiller for NoopKiller {
+        fn kill(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
+            Box::new(NoopKiller)
+        }
+    }
+
+    fn config() -> ShutdownConfig {
+        ShutdownConfig {
+            hangup_timeout_ms: 300,
+            terminate_timeout_ms: 300,
+            ..ShutdownConfig::default()
+        }
+    }
+
+    #[test]
+    fn hangup_is_tried_first() {
+        let (pid, exit) =
+            spawn_group("trap 'exit 3' HUP; echo ready; while :; do sleep 0.05; done");
+        let end = stop_process(Some(pid), None, &exit, &config(), &mut NoopKiller);
+        assert_eq!(
+            end,
+            ProcessEnd {
+                method: StopMethod::Hangup,
+                exited: true,
+                code: Some(3),
+                signal: None,
+            }
+        );
+    }
+
+    #[test]
+    fn ignored_signals_escalate_to_kill() {
+        let (pid, exit) = spawn_group("trap '' HUP TERM; echo ready; while :; do sleep 0.05; done");
+        let end = stop_process(Some(pid), None, &exit, &config(), &mut NoopKiller);
+        assert_eq!(end.method, StopMethod::Kill);
+        assert!(end.exited);
+        assert_eq!(end.code, None);
+        assert_eq!(end.signal.as_deref(), Some("SIGKILL"));
+
+        let end = stop_process(Some(pid), None, &exit, &config(), &mut NoopKiller);
+        assert_eq!(end.method, StopMethod::AlreadyExited);
+    }
+
+    #[test]
+    fn foreground_job_in_its_own_group_is_signalled() {
+        let (pid, exit) =
+            spawn_group("trap 'exit 3' HUP; echo ready; while :; do sleep 0.05; done");
+        let (job, job_exit) =
+            spawn_group("trap 'exit 4' HUP; echo ready; while :; do sleep 0.05; done");
+        let end = stop_process(Some(pid), Some(job), &exit, &config(), &mut NoopKiller);
+        assert_eq!(end.method, StopMethod::Hangup);
+        let job_end = job_exit
+            .wait(Duration::from_secs(1))
+            .expect("job should exit");
+        assert_eq!(job_end.code, Some(4));
+    }
+}
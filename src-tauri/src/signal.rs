@@ -0,0 +1,97 @@
+#[cfg(unix)]
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("WINCH", libc::SIGWINCH),
+];
+
+#[cfg(unix)]
+const FAULT_SIGNALS: &[(&str, libc::c_int)] = &[
+    ("SEGV", libc::SIGSEGV),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("ILL", libc::SIGILL),
+    ("PIPE", libc::SIGPIPE),
+    ("TRAP", libc::SIGTRAP),
+    ("SYS", libc::SIGSYS),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+];
+
+#[cfg(unix)]
+pub fn parse_signal(name: &str) -> Result<libc::c_int, String> {
+    let upper = name.trim().to_ascii_uppercase();
+    let short = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(candidate, _)| *candidate == short)
+        .map(|(_, signal)| *signal)
+        .ok_or_else(|| format!("unsupported signal: {name}"))
+}
+
+// portable-pty only reports the strsignal(3) description ("Killed"), so map it back to a name.
+#[cfg(unix)]
+pub fn signal_name(description: &str) -> Option<String> {
+    SIGNALS
+        .iter()
+        .chain(FAULT_SIGNALS)
+        .find(|(_, signal)| {
+            let text = unsafe { libc::strsignal(*signal) };
+            !text.is_null()
+                && unsafe { std::ffi::CStr::from_ptr(text) }.to_string_lossy() == description
+        })
+        .map(|(name, _)| format!("SIG{name}"))
+}
+
+#[cfg(unix)]
+pub fn send_to_group(pgid: u32, signal: libc::c_int) -> Result<(), String> {
+    let pgid = i32::try_from(pgid).map_err(|_| format!("invalid process group: {pgid}"))?;
+    if unsafe { libc::kill(-pgid, signal) } != 0 {
+        return Err(format!(
+            "failed to signal process group {pgid}: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_names_are_case_insensitive_with_optional_prefix() {
+        assert_eq!(parse_signal("SIGINT"), Ok(libc::SIGINT));
+        assert_eq!(parse_signal("tstp"), Ok(libc::SIGTSTP));
+        assert_eq!(parse_signal(" SigUsr1 "), Ok(libc::SIGUSR1));
+        assert!(parse_signal("SIGSEGV").is_err());
+        assert!(parse_signal("9").is_err());
+    }
+
+    #[test]
+    fn signal_descriptions_map_back_to_names() {
+        let description = |signal| {
+            let text = unsafe { std::ffi::CStr::from_ptr(libc::strsignal(signal)) };
+            text.to_string_lossy().into_owned()
+        };
+        assert_eq!(
+            signal_name(&description(libc::SIGKILL)).as_deref(),
+            Some("SIGKILL")
+        );
+        assert_eq!(
+            signal_name(&description(libc::SIGSEGV)).as_deref(),
+            Some("SIGSEGV")
+        );
+        assert_eq!(signal_name("Not a signal"), None);
+    }
+}
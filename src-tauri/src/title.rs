@@ -0,0 +1,234 @@
+use std::env;
+
+use tauri::{AppHandle, Manager};
+
+use crate::{pty::SessionId, state::AppState};
+
+const FALLBACK_TITLE: &str = "d3term";
+const SEPARATORS: &str = "—–-|:·";
+
+enum Part {
+    Text(String),
+    Value(String),
+}
+
+impl Part {
+    fn has_content(&self) -> bool {
+        match self {
+            Part::Text(text) => text.chars().any(|ch| !is_decoration(ch)),
+            Part::Value(value) => !value.is_empty(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TitleTracker {
+    last: Option<(String, Option<String>)>,
+}
+
+impl TitleTracker {
+    pub fn changed(&mut self, title: &str, cwd: Option<&str>) -> bool {
+        if self
+            .last
+            .as_ref()
+            .is_some_and(|(last_title, last_cwd)| last_title == title && last_cwd.as_deref() == cwd)
+        {
+            return false;
+        }
+        self.last = Some((title.to_string(), cwd.map(str::to_string)));
+        true
+    }
+}
+
+pub fn update_window_title(
+    app: &AppHandle,
+    session_id: SessionId,
+    command: &str,
+    title: &str,
+    cwd: Option<&str>,
+) {
+    let state = app.state::<AppState>();
+    let template = state.config.current().window.title_template;
+    let Some(window) = state
+        .session
+        .window_label(session_id)
+        .and_then(|label| app.get_webview_window(&label))
+    else {
+        return;
+    };
+    let home = env::var("HOME").ok();
+    let rendered = render_title(&template, title, command, cwd, home.as_deref());
+    if let Err(err) = window.set_title(&rendered) {
+        eprintln!("failed to set window title: {err}");
+    }
+}
+
+pub fn render_title(
+    template: &str,
+    title: &str,
+    command: &str,
+    cwd: Option<&str>,
+    home: Option<&str>,
+) -> String {
+    let cwd = cwd.map(|cwd| shorten_home(cwd, home)).unwrap_or_default();
+    let mut parts = parse_template(template, title.trim(), command, &cwd);
+    for index in 0..parts.len() {
+        if matches!(&parts[index], Part::Value(value) if value.is_empty()) {
+            tidy_empty_placeholder(&mut parts, index);
+        }
+    }
+    let rendered = parts
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) | Part::Value(text) => text.as_str(),
+        })
+        .collect::<String>();
+    let rendered = rendered.trim();
+    if rendered.is_empty() {
+        FALLBACK_TITLE.to_string()
+    } else {
+        rendered.to_string()
+    }
+}
+
+// Placeholders are substituted in one pass, so text coming from the program is never expanded
+// again. Parts alternate text and values, starting and ending with text.
+fn parse_template(template: &str, title: &str, command: &str, cwd: &str) -> Vec<Part> {
+    let placeholders = [("{title}", title), ("{command}", command), ("{cwd}", cwd)];
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let candidate = &rest[open..];
+        match placeholders
+            .iter()
+            .find(|(name, _)| candidate.starts_with(name))
+        {
+            Some((name, value)) => {
+                text.push_str(&rest[..open]);
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Value(value.to_string()));
+                rest = &candidate[name.len()..];
+            }
+            None => {
+                text.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    parts.push(Part::Text(text));
+    parts
+}
+
+// Only the template text around an empty placeholder is tidied: its brackets, then the
+// separator that joined it to the content before (or after, when it comes first).
+fn tidy_empty_placeholder(parts: &mut [Part], index: usize) {
+    let has_content_before = parts[..index].iter().any(Part::has_content);
+    let (before, after) = parts.split_at_mut(index);
+    let (Some(Part::Text(previous)), Some(Part::Text(next))) =
+        (before.last_mut(), after.get_mut(1))
+    else {
+        return;
+    };
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if previous.ends_with(open) && next.starts_with(close) {
+            previous.pop();
+            next.remove(0);
+            break;
+        }
+    }
+    if has_content_before {
+        previous.truncate(previous.trim_end_matches(is_decoration).len());
+    } else {
+        *next = next.trim_start_matches(is_decoration).to_string();
+    }
+}
+
+fn is_decoration(ch: char) -> bool {
+    ch.is_whitespace() || SEPARATORS.contains(ch)
+}
+
+fn shorten_home(cwd: &str, home: Option<&str>) -> String {
+    let Some(home) = home
+        .map(|home| home.trim_end_matches('/'))
+        .filter(|home| !home.is_empty())
+    else {
+        return cwd.to_string();
+    };
+    match cwd.strip_prefix(home) {
+        Some("") => "~".to_string(),
+        Some(rest) if rest.starts_with('/') => format!("~{rest}"),
+        _ => cwd.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "{title} — {command} ({cwd})";
+
+    #[test]
+    fn template_expands_title_command_and_cwd() {
+        assert_eq!(
+            render_title(
+                TEMPLATE,
+                "vim",
+                "zsh -l",
+                Some("/home/me/src"),
+                Some("/home/me")
+            ),
+            "vim — zsh -l (~/src)"
+        );
+        assert_eq!(
+            render_title("{cwd}", "", "zsh", Some("/home/me"), Some("/home/me/")),
+            "~"
+        );
+        assert_eq!(
+            render_title("{cwd}", "", "zsh", Some("/home/mel"), Some("/home/me")),
+            "/home/mel"
+        );
+    }
+
+    #[test]
+    fn empty_placeholders_are_tidied_up() {
+        assert_eq!(render_title(TEMPLATE, "", "zsh", None, None), "zsh");
+        assert_eq!(render_title("{title}", " ", "zsh", None, None), "d3term");
+        assert_eq!(
+            render_title(TEMPLATE, "vim", "", Some("/tmp"), None),
+            "vim (/tmp)"
+        );
+        assert_eq!(
+            render_title("{title} [{command}]", "vim", "", None, None),
+            "vim"
+        );
+    }
+
+    #[test]
+    fn program_titles_are_left_as_they_are() {
+        assert_eq!(
+            render_title(TEMPLATE, "{cwd} {command}", "zsh", Some("/tmp"), None),
+            "{cwd} {command} — zsh (/tmp)"
+        );
+        assert_eq!(
+            render_title(TEMPLATE, "main() - gdb", "zsh", None, None),
+            "main() - gdb — zsh"
+        );
+        assert_eq!(render_title("{title}", "-zsh", "zsh", None, None), "-zsh");
+        assert_eq!(
+            render_title("{title} | {cwd}", "vim  :w", "zsh", None, None),
+            "vim  :w"
+        );
+    }
+
+    #[test]
+    fn tracker_reports_only_changes() {
+        let mut tracker = TitleTracker::default();
+        assert!(tracker.changed("", None));
+        assert!(!tracker.changed("", None));
+        assert!(tracker.changed("", Some("/tmp")));
+        assert!(tracker.changed("vim", Some("/tmp")));
+        assert!(!tracker.changed("vim", Some("/tmp")));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use vte::{Parser, Perform};
+
+use crate::config::{LoggingConfig, TranscriptFormat};
+
+#[derive(Default)]
+struct PlainText {
+    text: Vec<u8>,
+}
+
+impl Perform for PlainText {
+    fn print(&mut self, c: char) {
+        let mut buffer = [0_u8; 4];
+        self.text
+            .extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+    }
+
+    fn execute(&mut self, byte: u8) {
+        if matches!(byte, b'\n' | b'\t') {
+            self.text.push(byte);
+        }
+    }
+}
+
+pub struct AnsiStripper {
+    parser: Parser,
+    performer: PlainText,
+}
+
+impl AnsiStripper {
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            performer: PlainText::default(),
+        }
+    }
+
+    pub fn strip(&mut self, bytes: &[u8]) -> Vec<u8> {
+        self.parser.advance(&mut self.performer, bytes);
+        std::mem::take(&mut self.performer.text)
+    }
+}
+
+pub struct TranscriptLog {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_file_bytes: u64,
+    max_files: usize,
+    stripper: Option<AnsiStripper>,
+}
+
+impl TranscriptLog {
+    pub fn create(path: PathBuf, config: &LoggingConfig) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_file_bytes: config.max_file_bytes,
+            max_files: config.max_files,
+            stripper: match config.format {
+                TranscriptFormat::Raw => None,
+                TranscriptFormat::Plain => Some(AnsiStripper::new()),
+            },
+        })
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let stripped;
+        let bytes = match self.stripper.as_mut() {
+            Some(stripper) => {
+                stripped = stripper.strip(bytes);
+                &stripped[..]
+            }
+            None => bytes,
+        };
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        if self.max_file_bytes > 0
+            && self.written > 0
+            && self.written + bytes.len() as u64 > self.max_file_bytes
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.path, self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = open_append(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+pub fn render_file_name(
+    template: &str,
+    now: &DateTime<Local>,
+    session_id: u64,
+    command: &str,
+) -> String {
+    let name = template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+        .replace("{session_id}", &session_id.to_string())
+        .replace("{command}", &command_slug(command));
+    name.replace(['/', '\\'], "_")
+}
+
+fn command_slug(command: &str) -> String {
+    let program = command.split_whitespace().next().unwrap_or_default();
+    let program = program.rsplit('/').next().unwrap_or_default();
+    let slug = program
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if slug.is_empty() {
+        "session".to_string()
+    } else {
+        slug
+    }
+}
+
+pub fn resolve_log_dir(state_dir: &Path, directory: &str) -> PathBuf {
+    let directory = directory.trim();
+    if directory.is_empty() {
+        return state_dir.join("logs");
+    }
+    state_dir.join(directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("d3term-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn stripper_removes_escape_sequences_across_reads() {
+        let mut stripper = AnsiStripper::new();
+        let mut text = stripper.strip(b"\x1b[1;3");
+        text.extend(stripper.strip(b"2mok\x1b[0m\r\n\x1b]0;title\x07"));
+        text.extend(stripper.strip("日本".as_bytes()));
+        assert_eq!(String::from_utf8(text).expect("utf-8"), "ok\n日本");
+    }
+
+    #[test]
+    fn file_name_template_expands_placeholders() {
+        let now = Local
+            .with_ymd_and_hms(2024, 5, 6, 7, 8, 9)
+            .single()
+            .expect("valid time");
+        assert_eq!(
+            render_file_name(
+                "{date}-{time}-session{session_id}-{command}.log",
+                &now,
+                3,
+                "/usr/bin/zellij attach -c d3term"
+            ),
+            "2024-05-06-070809-session3-zellij.log"
+        );
+        assert_eq!(
+            render_file_name("{date}/{command}.log", &now, 1, ""),
+            "2024-05-06_session.log"
+        );
+    }
+
+    #[test]
+    fn log_rotates_when_size_limit_is_exceeded() {
+        let dir = temp_dir("transcript-rotate");
+        let config = LoggingConfig {
+            enabled: true,
+            format: TranscriptFormat::Raw,
+            max_file_bytes: 8,
+            max_files: 2,
+            ..LoggingConfig::default()
+        };
+        let path = dir.join("session.log");
+        let mut log = TranscriptLog::create(path.clone(), &config).expect("create log");
+        for chunk in [&b"aaaaaa"[..], b"bbbbbb", b"cccccc", b"dddddd"] {
+            log.write(chunk).expect("write log");
+        }
+
+        assert_eq!(fs::read(&path).expect("current"), b"dddddd");
+        assert_eq!(fs::read(rotated_path(&path, 1)).expect("first"), b"cccccc");
+        assert_eq!(fs::read(rotated_path(&path, 2)).expect("second"), b"bbbbbb");
+        assert!(!rotated_path(&path, 3).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,113 @@
+use std::str;
+
+const REPLACEMENT: &str = "\u{FFFD}";
+
+#[derive(Debug, Default)]
+pub struct Utf8StreamDecoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8StreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input.as_slice();
+        loop {
+            match str::from_utf8(rest) {
+                Ok(valid) => {
+                    output.push_str(valid);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, after_valid) = rest.split_at(err.valid_up_to());
+                    output.push_str(&String::from_utf8_lossy(valid));
+                    match err.error_len() {
+                        Some(invalid_len) => {
+                            output.push_str(REPLACEMENT);
+                            rest = &after_valid[invalid_len..];
+                        }
+                        None => {
+                            self.pending.extend_from_slice(after_valid);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    pub fn finish(&mut self) -> String {
+        if self.pending.is_empty() {
+            return String::new();
+        }
+        self.pending.clear();
+        REPLACEMENT.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_in_chunks(bytes: &[u8], chunk_sizes: &[usize]) -> String {
+        let mut decoder = Utf8StreamDecoder::new();
+        let mut output = String::new();
+        let mut offset = 0;
+        for size in chunk_sizes.iter().cycle() {
+            if offset >= bytes.len() {
+                break;
+            }
+            let end = (offset + size).min(bytes.len());
+            output.push_str(&decoder.decode(&bytes[offset..end]));
+            offset = end;
+        }
+        output.push_str(&decoder.finish());
+        output
+    }
+
+    #[test]
+    fn split_at_every_offset_preserves_multibyte_text() {
+        let text = "プロンプト$ ls 日本語.txt 🍣\r\n";
+        let bytes = text.as_bytes();
+        for split in 0..=bytes.len() {
+            let mut decoder = Utf8StreamDecoder::new();
+            let mut output = decoder.decode(&bytes[..split]);
+            output.push_str(&decoder.decode(&bytes[split..]));
+            output.push_str(&decoder.finish());
+            assert_eq!(output, text, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn byte_by_byte_and_odd_chunks_preserve_text() {
+        let text = "端末 ✓ αβγ 🍣🍺";
+        for chunk in [1, 2, 3, 5, 7] {
+            assert_eq!(decode_in_chunks(text.as_bytes(), &[chunk]), text);
+        }
+        assert_eq!(decode_in_chunks(text.as_bytes(), &[1, 4, 2]), text);
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced_without_dropping_following_text() {
+        let mut decoder = Utf8StreamDecoder::new();
+        let output = decoder.decode(b"ok\xffnext\xc3(");
+        assert_eq!(output, "ok\u{FFFD}next\u{FFFD}(");
+        assert_eq!(decoder.finish(), "");
+    }
+
+    #[test]
+    fn incomplete_tail_is_reported_on_finish() {
+        let mut decoder = Utf8StreamDecoder::new();
+        let bytes = "あ".as_bytes();
+        assert_eq!(decoder.decode(&bytes[..2]), "");
+        assert_eq!(decoder.finish(), "\u{FFFD}");
+        assert_eq!(decoder.decode(b"a"), "a");
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::pty::SessionId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StartCwd {
+    Path(String),
+    Session(SessionId),
+}
+
+pub fn expand_path<F>(input: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let input = input.trim();
+    let (mut expanded, rest) = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            (lookup("HOME").unwrap_or_default(), rest)
+        }
+        _ => (String::new(), input),
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '$' {
+            expanded.push(ch);
+            continue;
+        }
+        let braced = chars.next_if_eq(&'{').is_some();
+        let mut name = String::new();
+        while let Some(next) = chars.next_if(|next| next.is_ascii_alphanumeric() || *next == '_') {
+            name.push(next);
+        }
+        if braced && chars.next_if_eq(&'}').is_none() {
+            expanded.push_str("${");
+            expanded.push_str(&name);
+            continue;
+        }
+        if name.is_empty() {
+            expanded.push('$');
+            if braced {
+                expanded.push_str("{}");
+            }
+            continue;
+        }
+        expanded.push_str(&lookup(&name).unwrap_or_default());
+    }
+    expanded
+}
+
+pub fn resolve_cwd<F>(requested: Option<&str>, lookup: F) -> (Option<PathBuf>, Option<String>)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let home = lookup("HOME")
+        .filter(|home| !home.trim().is_empty())
+        .map(PathBuf::from)
+        .filter(|home| home.is_dir());
+    let Some(requested) = requested.filter(|requested| !requested.trim().is_empty()) else {
+        return (home, None);
+    };
+
+    let expanded = expand_path(requested, &lookup);
+    let path = Path::new(&expanded);
+    if path.is_absolute() && path.is_dir() {
+        return (Some(path.to_path_buf()), None);
+    }
+    let warning = match home.as_ref() {
+        Some(home) => format!(
+            "作業ディレクトリ {expanded} が見つからないため {} で起動します",
+            home.display()
+        ),
+        None => format!("作業ディレクトリ {expanded} が見つかりません"),
+    };
+    (home, Some(warning))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some(std::env::temp_dir().display().to_string()),
+            "PROJECT" => Some("d3term".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn tilde_and_variables_are_expanded() {
+        let home = std::env::temp_dir().display().to_string();
+        assert_eq!(expand_path("~", lookup), home);
+        assert_eq!(
+            expand_path("~/src/$PROJECT", lookup),
+            format!("{home}/src/d3term")
+        );
+        assert_eq!(
+            expand_path("/srv/${PROJECT}-data", lookup),
+            "/srv/d3term-data"
+        );
+        assert_eq!(expand_path("/srv/$MISSING/x", lookup), "/srv//x");
+        assert_eq!(
+            expand_path("~user/a$ ${broken", lookup),
+            "~user/a$ ${broken"
+        );
+    }
+
+    #[test]
+    fn missing_directories_fall_back_to_home() {
+        let home = std::env::temp_dir();
+        assert_eq!(resolve_cwd(None, lookup), (Some(home.clone()), None));
+        assert_eq!(resolve_cwd(Some("~"), lookup), (Some(home.clone()), None));
+
+        let (cwd, warning) = resolve_cwd(Some("/definitely/not/here"), lookup);
+        assert_eq!(cwd, Some(home));
+        assert!(warning.is_some_and(|warning| warning.contains("/definitely/not/here")));
+
+        let (_, warning) = resolve_cwd(Some("relative/dir"), lookup);
+        assert!(warning.is_some());
+    }
+}
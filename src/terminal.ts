@@ -1,5 +1,6 @@
-import { invoke, isTauri } from "@tauri-apps/api/core";
+import { Channel, invoke, isTauri } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { FitAddon } from "@xterm/addon-fit";
 import { Unicode11Addon } from "@xterm/addon-unicode11";
 import { WebLinksAddon } from "@xterm/addon-web-links";
@@ -13,12 +14,14 @@ import {
 } from "./config-client";
 import "@xterm/xterm/css/xterm.css";
 
-interface PtyDataPayload {
-  data: string;
-}
-
 interface SessionExitPayload {
+  session_id: number;
+  generation: number;
   code: number | null;
+  signal: string | null;
+  killed_by_us: boolean;
+  runtime_ms: number;
+  error: string | null;
 }
 
 interface WarningPayload {
@@ -26,11 +29,48 @@ interface WarningPayload {
 }
 
 interface SessionInfo {
+  session_id: number;
+  generation: number;
   pid: number | null;
   command: string;
+  profile: string | null;
   fallback_used: boolean;
 }
 
+interface PlaybackInfo extends SessionInfo {
+  cols: number;
+  rows: number;
+}
+
+interface PlaybackResizePayload {
+  session_id: number;
+  generation: number;
+  cols: number;
+  rows: number;
+}
+
+interface ForegroundProcess {
+  session_id: number;
+  pgid: number;
+  name: string | null;
+  idle: boolean;
+}
+
+interface CloseRequestedPayload {
+  processes: ForegroundProcess[];
+}
+
+interface PlaybackState {
+  sessionId: number;
+  generation: number;
+  paused: boolean;
+  speed: number;
+}
+
+const SESSION_STORAGE_KEY = "d3term:session-id";
+
+const PLAYBACK_SPEEDS = [0.25, 0.5, 1, 2, 4, 8, 16];
+
 const DARK_THEME = {
   background: "#0b1020",
   foreground: "#d4d9e5",
@@ -79,6 +119,17 @@ const LIGHT_THEME = {
   brightWhite: "#ffffff",
 };
 
+function describeExit(payload: SessionExitPayload): string {
+  if (payload.error !== null) {
+    return `[process exited: unknown (${payload.error})]`;
+  }
+  if (payload.signal !== null) {
+    return `[process killed by ${payload.signal}]`;
+  }
+  const codeText = payload.code === null ? "unknown" : String(payload.code);
+  return `[process exited: ${codeText}]`;
+}
+
 export class D3TermApp {
   private terminal: Terminal;
 
@@ -102,6 +153,16 @@ export class D3TermApp {
 
   private backendAvailable = isTauri();
 
+  private sessionId: number | null = null;
+
+  private generation: number | null = null;
+
+  private dataChannel: Channel<ArrayBuffer> | null = null;
+
+  private unackedBytes = 0;
+
+  private playback: PlaybackState | null = null;
+
   constructor(
     private readonly terminalContainer: HTMLElement,
     private readonly warningContainer: HTMLElement,
@@ -176,23 +237,32 @@ export class D3TermApp {
       this.warningTimerId = null;
     }
 
-    if (this.backendAvailable) {
-      await invoke("stop_session").catch(() => undefined);
-    }
+    // The session outlives a reload and is re-attached via attach_session;
+    // the backend stops it when the window itself is destroyed.
     this.terminal.dispose();
   }
 
   private async registerBackendEvents(): Promise<void> {
     this.unlisteners.push(
-      await listen<PtyDataPayload>("pty:data", (event) => {
-        this.terminal.write(event.payload.data);
+      await listen<SessionExitPayload>("session:exit", (event) => {
+        if (this.isCurrentPlayback(event.payload)) {
+          this.terminal.writeln("\r\n[playback finished: press q to return]");
+          return;
+        }
+        if (!this.isCurrentSession(event.payload)) {
+          return;
+        }
+        this.terminal.writeln(`\r\n${describeExit(event.payload)}`);
       }),
     );
 
     this.unlisteners.push(
-      await listen<SessionExitPayload>("session:exit", (event) => {
-        const codeText = event.payload.code === null ? "unknown" : String(event.payload.code);
-        this.terminal.writeln(`\r\n[process exited: ${codeText}]`);
+      await listen<SessionInfo>("session:restarted", (event) => {
+        if (event.payload.session_id !== this.sessionId || this.playback !== null) {
+          return;
+        }
+        this.generation = event.payload.generation;
+        this.terminal.writeln("[session restarted]");
       }),
     );
 
@@ -209,6 +279,39 @@ export class D3TermApp {
         this.fitAndResize();
       }),
     );
+
+    this.unlisteners.push(
+      await listen<PlaybackResizePayload>("playback:resize", (event) => {
+        if (this.isCurrentPlayback(event.payload)) {
+          this.terminal.resize(event.payload.cols, event.payload.rows);
+        }
+      }),
+    );
+
+    this.unlisteners.push(
+      await listen<CloseRequestedPayload>("window:close-requested", (event) => {
+        const names = event.payload.processes
+          .map((process) => process.name ?? `pgid ${process.pgid}`)
+          .join(", ");
+        if (window.confirm(`${names} が実行中です。終了してウィンドウを閉じますか?`)) {
+          void invoke("close_window").catch((err) => {
+            this.showWarning(`ウィンドウを閉じられませんでした: ${String(err)}`);
+          });
+        }
+      }),
+    );
+
+    this.unlisteners.push(
+      await getCurrentWebview().onDragDropEvent((event) => {
+        if (event.payload.type !== "drop") {
+          return;
+        }
+        const path = event.payload.paths.find((candidate) => candidate.endsWith(".cast"));
+        if (path !== undefined) {
+          void this.startPlayback(path);
+        }
+      }),
+    );
   }
 
   private registerInputHandler(): void {
@@ -216,17 +319,45 @@ export class D3TermApp {
       return;
     }
     this.terminal.onData((data) => {
+      if (this.playback !== null) {
+        this.handlePlaybackKey(data);
+        return;
+      }
       this.inputBuffer += data;
       if (this.inputTimerId !== null) {
         return;
       }
       this.inputTimerId = window.setTimeout(() => {
-        const chunk = this.inputBuffer;
-        this.inputBuffer = "";
-        this.inputTimerId = null;
-        void invoke("write_stdin", { data: chunk }).catch(() => undefined);
+        this.flushInput();
       }, 4);
     });
+    this.terminal.onBinary((data) => {
+      this.flushInput();
+      if (this.sessionId === null || this.playback !== null) {
+        return;
+      }
+      const bytes = Array.from(data, (char) => char.charCodeAt(0) & 0xff);
+      void invoke("write_stdin_bytes", { sessionId: this.sessionId, data: bytes }).catch(
+        (err) => {
+          this.showWarning(`入力を送信できませんでした: ${String(err)}`);
+        },
+      );
+    });
+  }
+
+  private flushInput(): void {
+    if (this.inputTimerId !== null) {
+      window.clearTimeout(this.inputTimerId);
+      this.inputTimerId = null;
+    }
+    const chunk = this.inputBuffer;
+    this.inputBuffer = "";
+    if (chunk.length === 0 || this.sessionId === null) {
+      return;
+    }
+    void invoke("write_stdin", { sessionId: this.sessionId, data: chunk }).catch((err) => {
+      this.showWarning(`入力を送信できませんでした: ${String(err)}`);
+    });
   }
 
   private registerResizeHandling(): void {
@@ -248,22 +379,157 @@ export class D3TermApp {
   };
 
   private fitAndResize(): void {
+    if (this.playback !== null) {
+      return;
+    }
     this.fitAddon.fit();
     const cols = Math.max(2, this.terminal.cols);
     const rows = Math.max(1, this.terminal.rows);
-    void invoke("resize", { cols, rows }).catch(() => undefined);
+    if (this.sessionId === null) {
+      return;
+    }
+    void invoke("resize", { sessionId: this.sessionId, cols, rows }).catch(() => undefined);
   }
 
   private async startSession(): Promise<void> {
     const cols = Math.max(2, this.terminal.cols);
     const rows = Math.max(1, this.terminal.rows);
-    const info = await invoke<SessionInfo>("start_session", { cols, rows });
-    if (info.fallback_used) {
-      this.showWarning("指定コマンドを使えないため、通常シェルで起動しました。");
+    const onData = new Channel<ArrayBuffer>();
+    this.dataChannel = onData;
+    onData.onmessage = (data) => {
+      if (this.dataChannel !== onData) {
+        return;
+      }
+      const bytes = new Uint8Array(data);
+      this.terminal.write(bytes, () => {
+        if (this.dataChannel === onData) {
+          this.ackOutput(bytes.byteLength);
+        }
+      });
+    };
+    let info = await this.attachStoredSession(cols, rows, onData);
+    if (info === null) {
+      info = await invoke<SessionInfo>("start_session", { cols, rows, onData });
+      if (info.fallback_used) {
+        this.showWarning("指定コマンドを使えないため、通常シェルで起動しました。");
+      }
     }
+    this.sessionId = info.session_id;
+    this.generation = info.generation;
+    window.sessionStorage.setItem(SESSION_STORAGE_KEY, String(info.session_id));
+    this.ackOutput(0);
     this.terminal.focus();
   }
 
+  private async attachStoredSession(
+    cols: number,
+    rows: number,
+    onData: Channel<ArrayBuffer>,
+  ): Promise<SessionInfo | null> {
+    const stored = Number(window.sessionStorage.getItem(SESSION_STORAGE_KEY));
+    if (!Number.isInteger(stored) || stored <= 0) {
+      return null;
+    }
+    try {
+      return await invoke<SessionInfo>("attach_session", { sessionId: stored, cols, rows, onData });
+    } catch {
+      window.sessionStorage.removeItem(SESSION_STORAGE_KEY);
+      return null;
+    }
+  }
+
+  private async startPlayback(path: string): Promise<void> {
+    if (this.playback !== null) {
+      await this.stopPlayback();
+    }
+    this.flushInput();
+    const onData = new Channel<ArrayBuffer>();
+    // Detach the PTY output until playback ends; attach_session replays what was missed.
+    this.dataChannel = null;
+    onData.onmessage = (data) => {
+      if (this.playback !== null) {
+        this.terminal.write(new Uint8Array(data));
+      }
+    };
+    try {
+      const info = await invoke<PlaybackInfo>("start_playback", { path, onData });
+      this.playback = {
+        sessionId: info.session_id,
+        generation: info.generation,
+        paused: false,
+        speed: 1,
+      };
+      this.terminal.reset();
+      this.terminal.resize(info.cols, info.rows);
+      this.showWarning("再生中: Space で一時停止、+/- で速度変更、q で終了");
+    } catch (err) {
+      this.showWarning(`録画を再生できませんでした: ${String(err)}`);
+      await this.startSession();
+    }
+  }
+
+  private handlePlaybackKey(data: string): void {
+    const playback = this.playback;
+    if (playback === null) {
+      return;
+    }
+    if (data === "q") {
+      void this.stopPlayback();
+      return;
+    }
+    if (data === " ") {
+      playback.paused = !playback.paused;
+    } else if (data === "+" || data === "-") {
+      const index = PLAYBACK_SPEEDS.indexOf(playback.speed);
+      const next = index + (data === "+" ? 1 : -1);
+      playback.speed = PLAYBACK_SPEEDS[Math.min(Math.max(next, 0), PLAYBACK_SPEEDS.length - 1)];
+    } else {
+      return;
+    }
+    void invoke("control_playback", {
+      sessionId: playback.sessionId,
+      paused: playback.paused,
+      speed: playback.speed,
+    }).catch(() => undefined);
+    this.showWarning(playback.paused ? "一時停止中" : `再生速度: ${playback.speed}x`);
+  }
+
+  private async stopPlayback(): Promise<void> {
+    const playback = this.playback;
+    if (playback === null) {
+      return;
+    }
+    this.playback = null;
+    await invoke("stop_session", { sessionId: playback.sessionId }).catch(() => undefined);
+    this.terminal.reset();
+    this.fitAddon.fit();
+    await this.startSession();
+  }
+
+  private ackOutput(bytes: number): void {
+    this.unackedBytes += bytes;
+    if (this.sessionId === null || this.unackedBytes === 0) {
+      return;
+    }
+    const consumed = this.unackedBytes;
+    this.unackedBytes = 0;
+    void invoke("ack_output", { sessionId: this.sessionId, bytes: consumed }).catch(
+      () => undefined,
+    );
+  }
+
+  private isCurrentSession(payload: { session_id: number; generation: number }): boolean {
+    return payload.session_id === this.sessionId && payload.generation === this.generation;
+  }
+
+  private isCurrentPlayback(payload: { session_id: number; generation: number }): boolean {
+    return (
+      this.playback !== null &&
+      payload.session_id === this.playback.sessionId &&
+      payload.generation === this.playback.generation
+    );
+  }
+
   private applyConfig(next: AppConfig): void {
     this.config = next;
     this.terminal.options.fontFamily = next.terminal.font_family;